@@ -0,0 +1,297 @@
+// Routes synthetic designs of many small nets on large grids and reports the wall time and
+// the peak heap use of each, run with `cargo bench`. The sequential designs without a search
+// margin and at most 1000x1000 cells are also routed with the nested cell layout the router
+// used before the packed grid, as a baseline to compare against
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use mazerouting_lee::{Config, Coord, Maze, RoutingMode};
+
+// Heap use is measured by counting every allocation that goes through the global allocator
+struct CountingAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+// Small xorshift generator, the designs have to be the same on every run
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as usize
+    }
+}
+
+// Input file of a design whose nets have 2 or 3 pins within a window x window square, and the
+// pins of every net
+fn synthetic_design(
+    width: usize,
+    height: usize,
+    layers: usize,
+    nets: usize,
+    window: usize,
+) -> (String, Vec<Vec<Coord>>) {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let mut used = HashSet::new();
+    let mut input = format!("{}x{}\nlayers {}\n", width, height, layers);
+    let mut net_pins = vec![];
+    for n in 0..nets {
+        let (x0, y0) = (
+            rng.below(width - window + 1),
+            rng.below(height - window + 1),
+        );
+        write!(input, "net{}", n + 1).unwrap();
+        let pins = 2 + rng.below(2);
+        let mut placed = vec![];
+        while placed.len() < pins {
+            let pin = (
+                1 + rng.below(layers),
+                x0 + rng.below(window),
+                y0 + rng.below(window),
+            );
+            if used.insert(pin) {
+                write!(input, " ({},{},{})", pin.0, pin.1, pin.2).unwrap();
+                placed.push((pin.0 - 1, pin.1, pin.2));
+            }
+        }
+        input.push('\n');
+        net_pins.push(placed);
+    }
+    input.push_str("via_cost 10\nnonpreferred_direction_cost 5\n");
+    (input, net_pins)
+}
+
+// Cell of the nested layout, with the search costs kept in the grid itself
+#[derive(Clone, Copy, PartialEq)]
+enum NestedCell {
+    Free,
+    Routed,
+    Start,
+    Target(u32),
+    Candidate(u32),
+}
+
+// The router as it was before the packed grid: a Vec per layer and row, the parents of a search
+// in a HashMap and a scan of the whole grid after every search to clear its costs
+struct NestedMaze {
+    grid: Vec<Vec<Vec<NestedCell>>>, // [layer][row][col]
+    via_cost: u32,
+    nonpreferred_cost: u32,
+}
+
+impl NestedMaze {
+    fn new(width: usize, height: usize, layers: usize, via_cost: u32, nonpreferred: u32) -> Self {
+        NestedMaze {
+            grid: vec![vec![vec![NestedCell::Free; height]; width]; layers],
+            via_cost,
+            nonpreferred_cost: nonpreferred,
+        }
+    }
+
+    fn neighbours(&self, (l, r, c): Coord) -> Vec<(Coord, u32)> {
+        let moves = [
+            (0, -1, 0),
+            (0, 1, 0),
+            (0, 0, -1),
+            (0, 0, 1),
+            (-1, 0, 0),
+            (1, 0, 0),
+        ];
+        let mut result = vec![];
+        for (dl, dr, dc) in moves {
+            let (nl, nr, nc) = (l as isize + dl, r as isize + dr, c as isize + dc);
+            if nl < 0 || nr < 0 || nc < 0 {
+                continue;
+            }
+            let (nl, nr, nc) = (nl as usize, nr as usize, nc as usize);
+            let Some(cell) = self.grid.get(nl).and_then(|layer| layer.get(nr)?.get(nc)) else {
+                continue;
+            };
+            if matches!(cell, NestedCell::Routed | NestedCell::Start) {
+                continue;
+            }
+            let mut cost = if dl != 0 { self.via_cost } else { 1 };
+            if (l % 2 == 0 && dr != 0) || (l % 2 != 0 && dc != 0) {
+                cost += self.nonpreferred_cost;
+            }
+            result.push(((nl, nr, nc), cost));
+        }
+        result
+    }
+
+    // Grows the tree in `sources` to the cheapest target, false if none can be reached
+    fn dijkstra(&mut self, sources: &mut Vec<Coord>) -> bool {
+        let mut queue = BinaryHeap::new();
+        let mut parent: HashMap<Coord, Coord> = HashMap::new();
+        for &source in sources.iter() {
+            queue.push((Reverse(0), source));
+        }
+        while let Some((Reverse(cost), (l, r, c))) = queue.pop() {
+            if let NestedCell::Target(_) = self.grid[l][r][c] {
+                let mut current = (l, r, c);
+                while self.grid[current.0][current.1][current.2] != NestedCell::Start {
+                    self.grid[current.0][current.1][current.2] = NestedCell::Start;
+                    sources.push(current);
+                    current = parent[&current];
+                }
+                return true;
+            }
+            for ((nl, nr, nc), step) in self.neighbours((l, r, c)) {
+                let new_cost = cost + step;
+                let cell = &mut self.grid[nl][nr][nc];
+                *cell = match *cell {
+                    NestedCell::Free => NestedCell::Candidate(new_cost),
+                    NestedCell::Candidate(old) if new_cost < old => NestedCell::Candidate(new_cost),
+                    NestedCell::Target(old) if new_cost < old => NestedCell::Target(new_cost),
+                    _ => continue,
+                };
+                parent.insert((nl, nr, nc), (l, r, c));
+                queue.push((Reverse(new_cost), (nl, nr, nc)));
+            }
+        }
+        false
+    }
+
+    fn clear_candidates(&mut self) {
+        for cell in self.grid.iter_mut().flatten().flatten() {
+            if let NestedCell::Candidate(_) = *cell {
+                *cell = NestedCell::Free;
+            }
+        }
+    }
+
+    // Routes every net from its first pin, the number of nets that could not be completed
+    fn process_nets(&mut self, nets: &[Vec<Coord>]) -> usize {
+        let mut failed = 0;
+        for pins in nets {
+            for &(l, r, c) in pins {
+                self.grid[l][r][c] = NestedCell::Target(u32::MAX);
+            }
+            let (l, r, c) = pins[0];
+            self.grid[l][r][c] = NestedCell::Start;
+            let mut sources = vec![pins[0]];
+            let mut complete = true;
+            for _ in 1..pins.len() {
+                complete &= self.dijkstra(&mut sources);
+                self.clear_candidates();
+            }
+            for &(l, r, c) in &sources {
+                self.grid[l][r][c] = NestedCell::Routed;
+            }
+            failed += usize::from(!complete);
+        }
+        failed
+    }
+}
+
+fn main() {
+    let sequential = RoutingMode::Sequential;
+    let parallel = RoutingMode::Parallel {
+        threads: 0,
+        margin: 5,
+    };
+    // (width, height, layers, nets, pin window, search margin, mode), the die-wide nets come last
+    let designs = [
+        (500, 500, 2, 200, 20, None, sequential),
+        (1000, 1000, 4, 200, 20, None, sequential),
+        (2000, 2000, 4, 100, 20, None, sequential),
+        (2000, 2000, 4, 100, 20, Some(5), sequential),
+        (2000, 2000, 4, 2000, 20, Some(5), sequential),
+        (2000, 2000, 4, 2000, 20, Some(5), parallel),
+        (1000, 1000, 4, 10, 1000, None, sequential),
+    ];
+    println!(
+        "grid            nets  window  margin  mode        layout   time (ms)   peak heap (MiB)   failed"
+    );
+    for (width, height, layers, nets, window, margin, mode) in designs {
+        let path = std::env::temp_dir().join(format!(
+            "mazerouting_bench_{}x{}x{}_{}.txt",
+            width, height, layers, window
+        ));
+        let (input, net_pins) = synthetic_design(width, height, layers, nets, window);
+        fs::write(&path, input).unwrap();
+        let config = Config::build(path.to_str().unwrap()).unwrap();
+        let row = |layout: &str, elapsed: Duration, peak: usize, failed: usize| {
+            println!(
+                "{:<15} {:>4} {:>7} {:>7}  {:<10}  {:<6} {:>11} {:>17.1} {:>8}",
+                format!("{}x{}x{}", width, height, layers),
+                nets,
+                window,
+                margin.map_or("-".to_string(), |margin| margin.to_string()),
+                match mode {
+                    RoutingMode::Parallel { .. } => "parallel",
+                    _ => "sequential",
+                },
+                layout,
+                elapsed.as_millis(),
+                peak as f64 / (1024.0 * 1024.0),
+                failed
+            )
+        };
+
+        // The nested layout needs several GiB and minutes beyond a million cells per layer
+        if margin.is_none()
+            && matches!(mode, RoutingMode::Sequential)
+            && width * height <= 1_000_000
+        {
+            let before = CURRENT.load(Ordering::Relaxed);
+            PEAK.store(before, Ordering::Relaxed);
+            let start = Instant::now();
+            let mut nested = NestedMaze::new(
+                width,
+                height,
+                layers,
+                config.via_cost as u32,
+                config.nonpreferred_direction_cost as u32,
+            );
+            let failed = nested.process_nets(&net_pins);
+            let elapsed = start.elapsed();
+            drop(nested);
+            row(
+                "nested",
+                elapsed,
+                PEAK.load(Ordering::Relaxed) - before,
+                failed,
+            );
+        }
+
+        let before = CURRENT.load(Ordering::Relaxed);
+        PEAK.store(before, Ordering::Relaxed);
+        let start = Instant::now();
+        let mut maze = Maze::from_config(&config).unwrap();
+        maze.set_search_margin(margin);
+        maze.set_routing_mode(mode);
+        let result = maze.process_nets(&config.nets).unwrap();
+        let elapsed = start.elapsed();
+        let peak = PEAK.load(Ordering::Relaxed) - before;
+        row("packed", elapsed, peak, result.failed_nets().len());
+        fs::remove_file(&path).ok();
+    }
+}
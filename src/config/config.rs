@@ -1,20 +1,37 @@
-use std::error::Error;
+use std::cell::Cell;
+use std::collections::HashSet;
 use std::fs;
 use std::result::Result;
 
-static DEFAULT_VIA_COST: i32 = 19;
-static DEFAULT_DIRECTION_CHANGE: i32 = 5;
+pub(crate) static DEFAULT_VIA_COST: i32 = 19;
+pub(crate) static DEFAULT_DIRECTION_CHANGE: i32 = 5;
+static DEFAULT_LAYERS: usize = 2;
 
 use crate::Coord;
-use crate::{Net, Pin};
+use crate::{Blockage, LayerSpec, Net, Pin, PreferredDirection, RouterError, ViaRules};
 #[derive(Debug)]
 pub struct Config {
     pub grid_width: u16,
     pub grid_height: u16,
-    pub obstacles: Vec<Coord>,
+    pub layers: usize,
+    pub obstacles: Vec<Blockage>,
     pub nets: Vec<Net>, // each net has a vector of pins
     pub via_cost: i32, // assuming a very high cost (can have a default value if not specified by the user)
     pub nonpreferred_direction_cost: i32,
+    pub layer_stack: Vec<LayerSpec>, // one entry per layer
+    pub via_rules: ViaRules,
+    pub via_obstacles: Vec<Blockage>, // cells where wires may run but no via may be placed
+}
+
+// A LAYER line as written, the penalty falls back to nonpreferred_direction_cost
+type LayerLine = (usize, PreferredDirection, Option<u32>, Option<usize>); // layer, direction, penalty, spacing
+
+// A VIA line as written
+#[derive(Debug, PartialEq)]
+enum ViaLine {
+    Cost(usize, u32), // lower layer of the pair, cost
+    Stacking(bool),
+    Spacing(usize),
 }
 
 impl Config {
@@ -22,77 +39,376 @@ impl Config {
         let dims: Result<Vec<u16>, _> = line.split('x').map(|s| s.trim().parse::<u16>()).collect();
 
         match dims {
+            Ok(vec) if vec.len() == 2 && vec.contains(&0) => {
+                Err("Grid dimensions have to be at least 1x1")
+            }
             Ok(vec) if vec.len() == 2 => Ok((vec[0], vec[1])),
             _ => Err("Invalid dimension format. Expected format like 10x20."),
         }
     }
 
-    fn parse_obs(line: &str) -> Result<Coord, &'static str> {
-        let content = line
+    // Optional line right after the grid dimensions, e.g. layers 4
+    fn parse_layers<'a, I>(lines: &mut std::iter::Peekable<I>) -> Result<usize, &'static str>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        match lines.peek() {
+            Some(line) if line.trim_start().starts_with("layers") => lines
+                .next()
+                .and_then(|line| line.split_whitespace().nth(1))
+                .and_then(|val| val.parse::<usize>().ok())
+                .filter(|&layers| layers > 0)
+                .ok_or("Invalid layers format. Expected format like layers 4"),
+            _ => Ok(DEFAULT_LAYERS),
+        }
+    }
+
+    fn parse_layer_spec(line: &str) -> Result<LayerLine, &'static str> {
+        let err = "Invalid LAYER format. Expected format like LAYER 1 horizontal 5 spacing 1";
+        let mut words = line.split_whitespace();
+        if words.next() != Some("LAYER") {
+            return Err(err);
+        }
+        let layer = words
+            .next()
+            .and_then(|val| val.parse::<usize>().ok())
+            .and_then(|layer| layer.checked_sub(1))
+            .ok_or(err)?;
+        let direction = match words.next() {
+            Some("horizontal") => PreferredDirection::Horizontal,
+            Some("vertical") => PreferredDirection::Vertical,
+            Some("none") => PreferredDirection::None,
+            _ => return Err(err),
+        };
+        let mut words = words.peekable();
+        let penalty = match words.peek() {
+            Some(&"spacing") | None => None,
+            Some(val) => Some(val.parse::<u32>().map_err(|_| err)?),
+        };
+        if penalty.is_some() {
+            words.next();
+        }
+        let spacing = match words.next() {
+            Some("spacing") => Some(
+                words
+                    .next()
+                    .and_then(|val| val.parse::<usize>().ok())
+                    .ok_or(err)?,
+            ),
+            Some(_) => return Err(err),
+            None => None,
+        };
+        if words.next().is_some() {
+            return Err(err);
+        }
+        Ok((layer, direction, penalty, spacing))
+    }
+
+    fn parse_layer_stack<'a, I>(
+        lines: &mut std::iter::Peekable<I>,
+    ) -> Result<Vec<LayerLine>, &'static str>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut specs = Vec::new();
+        while let Some(line) = lines.peek() {
+            if !line.trim_start().starts_with("LAYER") {
+                break;
+            }
+            specs.push(Self::parse_layer_spec(lines.next().unwrap().trim())?);
+        }
+        Ok(specs)
+    }
+
+    // Layers without a LAYER line keep the alternating horizontal/vertical default
+    fn resolve_layer_stack(
+        layers: usize,
+        specs: &[LayerLine],
+        nonpreferred_direction_cost: u32,
+    ) -> Result<Vec<LayerSpec>, RouterError> {
+        let mut stack = LayerSpec::alternating(layers, nonpreferred_direction_cost);
+        for &(layer, direction, penalty, spacing) in specs {
+            let spec = stack
+                .get_mut(layer)
+                .ok_or(RouterError::UnknownLayer { layer, layers })?;
+            *spec = LayerSpec {
+                direction,
+                nonpreferred_cost: penalty.unwrap_or(nonpreferred_direction_cost),
+                min_spacing: spacing.unwrap_or(0),
+            };
+        }
+        Ok(stack)
+    }
+
+    // VIA 1 2 cost 15, VIA stacking off or VIA spacing 1
+    fn parse_via_rule(line: &str) -> Result<ViaLine, &'static str> {
+        let err = "Invalid VIA format. Expected format like VIA 1 2 cost 15, VIA stacking off or VIA spacing 1";
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["VIA", "stacking", "on"] => Ok(ViaLine::Stacking(true)),
+            ["VIA", "stacking", "off"] => Ok(ViaLine::Stacking(false)),
+            ["VIA", "spacing", spacing] => spacing
+                .parse::<usize>()
+                .map(ViaLine::Spacing)
+                .map_err(|_| err),
+            ["VIA", from, to, "cost", cost] => {
+                let layer = |val: &str| val.parse::<usize>().ok().filter(|&layer| layer > 0);
+                let (from, to) = layer(from).zip(layer(to)).ok_or(err)?;
+                if from.abs_diff(to) != 1 {
+                    return Err("A via joins two neighbouring layers");
+                }
+                let cost = cost.parse::<u32>().map_err(|_| err)?;
+                Ok(ViaLine::Cost(from.min(to) - 1, cost))
+            }
+            _ => Err(err),
+        }
+    }
+
+    fn parse_via_rules<'a, I>(
+        lines: &mut std::iter::Peekable<I>,
+    ) -> Result<Vec<ViaLine>, &'static str>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut rules = Vec::new();
+        while let Some(line) = lines.peek() {
+            if !line.trim_start().starts_with("VIA ") {
+                break;
+            }
+            rules.push(Self::parse_via_rule(lines.next().unwrap())?);
+        }
+        Ok(rules)
+    }
+
+    // Without VIA lines vias stack freely, need no spacing and all cost via_cost
+    fn resolve_via_rules(
+        layers: usize,
+        rules: &[ViaLine],
+        via_cost: u32,
+    ) -> Result<ViaRules, RouterError> {
+        let mut via_rules = ViaRules::uniform(layers, via_cost);
+        for rule in rules {
+            match *rule {
+                ViaLine::Cost(layer, cost) => {
+                    *via_rules
+                        .costs
+                        .get_mut(layer)
+                        .ok_or(RouterError::UnknownLayer {
+                            layer: layer + 1,
+                            layers,
+                        })? = cost;
+                }
+                ViaLine::Stacking(stacking) => via_rules.stacking = stacking,
+                ViaLine::Spacing(spacing) => via_rules.min_spacing = spacing,
+            }
+        }
+        Ok(via_rules)
+    }
+
+    // The inside of an OBS tuple: layer,x,y or layer,x1,y1,x2,y2, where * as the layer covers
+    // every layer. The corners may come in any order
+    fn parse_region(content: &str) -> Option<Blockage> {
+        let values: Vec<&str> = content.split(',').map(|s| s.trim()).collect();
+        let layer = match *values.first()? {
+            "*" => None,
+            layer => Some(layer.parse::<usize>().ok()?.checked_sub(1)?),
+        };
+        let nums: Vec<usize> = values[1..]
+            .iter()
+            .map(|s| s.parse::<usize>().ok())
+            .collect::<Option<_>>()?;
+        let (x1, y1, x2, y2) = match nums[..] {
+            [x, y] => (x, y, x, y),
+            [x1, y1, x2, y2] => (x1, y1, x2, y2),
+            _ => return None,
+        };
+        Some(Blockage {
+            name: None,
+            layer,
+            rows: (x1.min(x2), x1.max(x2)),
+            cols: (y1.min(y2), y1.max(y2)),
+        })
+    }
+
+    fn parse_obs(line: &str) -> Result<Blockage, &'static str> {
+        line.trim()
             .strip_prefix("OBS (")
             .and_then(|s| s.strip_suffix(")"))
-            .ok_or("Invalid OBS format")?;
-
-        let coordinates: Result<Vec<usize>, _> = content
-            .split(",")
-            .map(|s| s.trim().parse::<usize>())
-            .collect();
+            .and_then(Self::parse_region)
+            .ok_or("Invalid OBS format. Expected format like OBS (1,15,32), OBS (1,0,0,9,9) or OBS (*,15,32)")
+    }
 
-        match coordinates {
-            Ok(vec) if vec.len() == 3 => Ok((vec[0] - 1, vec[1], vec[2])),
-            _ => Err("Invalid OBS format. Expected format like OBS (1,15,32)"),
+    // A named region of one or more rectangles, e.g. BLOCKAGE ram0 (*,10,10,40,40) (1,41,10,45,12)
+    fn parse_blockage(line: &str) -> Result<Vec<Blockage>, &'static str> {
+        let err = "Invalid BLOCKAGE format. Expected format like BLOCKAGE ram0 (*,10,10,40,40)";
+        let rest = line.trim().strip_prefix("BLOCKAGE ").ok_or(err)?;
+        let (name, regions) = rest.split_once('(').ok_or(err)?;
+        let name = name.trim();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(err);
         }
+        regions
+            .split('(')
+            .map(|region| {
+                region
+                    .trim()
+                    .strip_suffix(')')
+                    .and_then(Self::parse_region)
+                    .map(|blockage| Blockage {
+                        name: Some(name.to_string()),
+                        ..blockage
+                    })
+                    .ok_or(err)
+            })
+            .collect()
     }
 
-    fn parse_all_obs<'a, I>(lines: &mut std::iter::Peekable<I>) -> Result<Vec<Coord>, &'static str>
+    fn parse_all_obs<'a, I>(
+        lines: &mut std::iter::Peekable<I>,
+    ) -> Result<Vec<Blockage>, &'static str>
     where
         I: Iterator<Item = &'a str>,
     {
-        let mut obstacles: Vec<Coord> = Vec::new();
+        let mut obstacles: Vec<Blockage> = Vec::new();
 
         while let Some(line) = lines.peek() {
-            if !line.trim_start().starts_with("OBS") {
+            let line = line.trim_start();
+            if line.starts_with("OBS") {
+                // If it matches, consume the line
+                let obs = Self::parse_obs(lines.next().unwrap())?;
+                obstacles.push(obs);
+            } else if line.starts_with("BLOCKAGE") {
+                obstacles.extend(Self::parse_blockage(lines.next().unwrap())?);
+            } else {
                 break;
             }
-
-            // If it matches, consume the line
-            let obs = Self::parse_obs(lines.next().unwrap())?;
-            obstacles.push(obs);
         }
         Ok(obstacles)
     }
 
+    // Same as an OBS line, but only vias are kept out of the cells
+    fn parse_via_obs(line: &str) -> Result<Blockage, &'static str> {
+        line.trim()
+            .strip_prefix("VIA")
+            .and_then(|obs| Self::parse_obs(obs).ok())
+            .ok_or("Invalid VIAOBS format. Expected format like VIAOBS (1,15,32) or VIAOBS (1,0,0,9,9)")
+    }
+
+    fn parse_all_via_obs<'a, I>(
+        lines: &mut std::iter::Peekable<I>,
+    ) -> Result<Vec<Blockage>, &'static str>
+    where
+        I: Iterator<Item = &'a str>,
+    {
+        let mut blockages: Vec<Blockage> = Vec::new();
+        while let Some(line) = lines.peek() {
+            if !line.trim_start().starts_with("VIAOBS") {
+                break;
+            }
+            blockages.push(Self::parse_via_obs(lines.next().unwrap())?);
+        }
+        Ok(blockages)
+    }
+
+    // The cells of a pin tuple: layer,x,y or layer,x1,y1,x2,y2, where the layer may be a range
+    // like 1-3. The first cell is the lowest layer at the smaller corner
+    fn parse_pin_shape(tuple: &str) -> Result<Vec<Coord>, &'static str> {
+        let values: Vec<&str> = tuple.split(',').map(|s| s.trim()).collect();
+        let layer = |val: &str| {
+            val.trim()
+                .parse::<usize>()
+                .map_err(|_| "Invalid int")?
+                .checked_sub(1) // for ease of indexing later
+                .ok_or("Layers are numbered from 1")
+        };
+        let (first, last) = values[0].split_once('-').unwrap_or((values[0], values[0]));
+        let (first, last) = (layer(first)?, layer(last)?);
+        let nums: Vec<usize> = values[1..]
+            .iter()
+            .map(|s| s.parse::<usize>().map_err(|_| "Invalid int"))
+            .collect::<Result<_, _>>()?;
+        let (x1, y1, x2, y2) = match nums[..] {
+            [x, y] => (x, y, x, y),
+            [x1, y1, x2, y2] => (x1, y1, x2, y2),
+            _ => return Err("Expected 3 or 5 values in the net pin tuple"),
+        };
+
+        let mut cells = vec![];
+        for l in first.min(last)..=first.max(last) {
+            for x in x1.min(x2)..=x1.max(x2) {
+                for y in y1.min(y2)..=y1.max(y2) {
+                    cells.push((l, x, y));
+                }
+            }
+        }
+        Ok(cells)
+    }
+
+    // A net line may end with its routing priority, e.g. net1 (1,0,0) (1,4,4) priority 3
     fn parse_net(line: &str) -> Result<Net, &'static str> {
         if !line.contains(" (") {
             return Err("Invalid net format");
         }
 
+        let (line, priority) = match line.trim().rsplit_once(" priority ") {
+            Some((pins, priority)) => (
+                pins,
+                priority
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| "Invalid net priority")?,
+            ),
+            None => (line, 0),
+        };
+
         let mut parts = line.trim().split("(");
 
         let net_name = parts.next().ok_or("Missing net name")?.trim().to_string();
+        if net_name.is_empty() {
+            return Err("Missing net name");
+        }
+        if net_name.contains(char::is_whitespace) {
+            return Err("Net names cannot contain whitespace");
+        }
 
+        // a pin is a tuple, or several tuples joined by +, e.g. VDD (1-2,0,0,0,9)+(3,0,9)
         let mut pins: Vec<Pin> = vec![];
+        let mut joined = false; // the last tuple ended with +, so this one is more of the same pin
+        let mut known: HashSet<Coord> = HashSet::new(); // cells of the last pin
         for part in parts {
-            if let Some(tuple) = part.trim().strip_suffix(')') {
-                let nums: Vec<&str> = tuple.split(',').map(|s| s.trim()).collect();
-                if nums.len() == 3 {
-                    let mut layer_num = nums[0].parse::<usize>().map_err(|_| "Invalid int")?;
-                    layer_num -= 1; // for ease of indexing later
-                    let pin_x = nums[1].parse::<usize>().map_err(|_| "Invalid int")?;
-                    let pin_y = nums[2].parse::<usize>().map_err(|_| "Invalid int")?;
-                    let coord = (layer_num, pin_x, pin_y);
-                    pins.push(Pin { coord: coord });
-                } else {
-                    return Err("Expected 3 values in the net pin tuple");
+            let part = part.trim();
+            let (part, joins_next) = match part.strip_suffix('+') {
+                Some(part) => (part.trim_end(), true),
+                None => (part, false),
+            };
+            let tuple = part.strip_suffix(')').ok_or("Invalid net tuple format")?;
+            let cells = Self::parse_pin_shape(tuple)?;
+            match pins.last_mut() {
+                Some(pin) if joined => {
+                    for cell in cells {
+                        if known.insert(cell) {
+                            pin.shape.push(cell);
+                        }
+                    }
+                }
+                _ => {
+                    known = cells.iter().copied().collect();
+                    pins.push(Pin {
+                        coord: cells[0],
+                        shape: cells[1..].to_vec(),
+                    })
                 }
-            } else {
-                return Err("Invalid net tuple format");
             }
+            joined = joins_next;
+        }
+        if joined {
+            return Err("A pin cannot end with +");
         }
-        return Ok(Net {
+        Ok(Net {
             _net_name: net_name,
-            pins: pins,
-        });
+            pins,
+            priority,
+        })
     }
 
     fn parse_all_nets<'a, I>(lines: &mut std::iter::Peekable<I>) -> Result<Vec<Net>, &'static str>
@@ -101,8 +417,9 @@ impl Config {
     {
         let mut nets: Vec<Net> = Vec::new();
 
+        // any line with pin tuples is a net, whatever its name
         while let Some(line) = lines.peek() {
-            if !line.trim_start().starts_with("net") {
+            if !line.contains('(') {
                 break;
             }
             let net: Net = Self::parse_net(lines.next().unwrap())?;
@@ -111,49 +428,105 @@ impl Config {
         Ok(nets)
     }
 
-    fn parse_extra_costs<'a, I>(lines: &mut I) -> (i32, i32)
+    // Costs that do not parse fall back to the defaults, negative ones are rejected as the router
+    // adds costs up unsigned
+    fn parse_extra_costs<'a, I>(lines: &mut I) -> Result<(i32, i32), &'static str>
     where
         I: Iterator<Item = &'a str>,
     {
-        let via_cost: i32 = lines
-            .next()
-            .and_then(|line| line.split_whitespace().nth(1)) // get the second word
-            .and_then(|val| val.parse::<i32>().ok())
-            .unwrap_or(DEFAULT_VIA_COST);
-
-        let direction_change_cost: i32 = lines
-            .next()
-            .and_then(|line| line.split_whitespace().nth(1)) // get the second word
-            .and_then(|val| val.parse::<i32>().ok())
-            .unwrap_or(DEFAULT_DIRECTION_CHANGE);
+        let mut cost = |default: i32| {
+            let cost = lines
+                .next()
+                .and_then(|line| line.split_whitespace().nth(1)) // get the second word
+                .and_then(|val| val.parse::<i32>().ok())
+                .unwrap_or(default);
+            if cost < 0 {
+                return Err("Costs cannot be negative");
+            }
+            Ok(cost)
+        };
+        let via_cost = cost(DEFAULT_VIA_COST)?;
+        let direction_change_cost = cost(DEFAULT_DIRECTION_CHANGE)?;
 
-        (via_cost, direction_change_cost)
+        Ok((via_cost, direction_change_cost))
     }
 
-    pub fn build(filename: &str) -> Result<Config, Box<dyn Error>> {
-        let contents = fs::read_to_string(filename)?; // ? delegates error handling to the caller
-        let mut lines = contents.lines().peekable();
+    // Obstacles and pins have to sit on one of the declared layers
+    fn validate_layers(
+        layers: usize,
+        obstacles: &[Blockage],
+        via_obstacles: &[Blockage],
+        nets: &[Net],
+    ) -> Result<(), RouterError> {
+        for obs in obstacles.iter().chain(via_obstacles) {
+            if let Some(layer) = obs.layer.filter(|&layer| layer >= layers) {
+                let coord = (layer, obs.rows.0, obs.cols.0);
+                return Err(RouterError::ObstacleOutOfRange { coord, layers });
+            }
+        }
+        for net in nets {
+            if let Some(&coord) = net.pin_cells().find(|cell| cell.0 >= layers) {
+                return Err(RouterError::PinOutOfRange {
+                    net: net._net_name.clone(),
+                    coord,
+                });
+            }
+        }
+        Ok(())
+    }
 
-        let first_line = lines.next().ok_or("Input file is empty")?;
-        let (grid_width, grid_height) = Self::parse_grid_dims(first_line)?;
-        let obstacles = Self::parse_all_obs(&mut lines)?;
-        let nets = Self::parse_all_nets(&mut lines)?;
-        let (via_cost, direction_change_cost) = Self::parse_extra_costs(&mut lines);
+    pub fn build(filename: &str) -> Result<Config, RouterError> {
+        let contents = fs::read_to_string(filename).map_err(|err| RouterError::Io {
+            path: filename.to_string(),
+            message: err.to_string(),
+        })?;
+
+        // Every section parser peeks at the line it is about to parse, so the number of lines
+        // pulled so far is the number of the line that failed
+        let lines_read = Cell::new(0);
+        let mut lines = contents
+            .lines()
+            .inspect(|_| lines_read.set(lines_read.get() + 1))
+            .peekable();
+        let at_line = |message| RouterError::Parse {
+            line: lines_read.get(),
+            message,
+        };
+
+        let first_line = lines.next().ok_or(RouterError::EmptyInput)?;
+        let (grid_width, grid_height) = Self::parse_grid_dims(first_line).map_err(at_line)?;
+        let layers = Self::parse_layers(&mut lines).map_err(at_line)?;
+        let layer_specs = Self::parse_layer_stack(&mut lines).map_err(at_line)?;
+        let via_lines = Self::parse_via_rules(&mut lines).map_err(at_line)?;
+        let obstacles = Self::parse_all_obs(&mut lines).map_err(at_line)?;
+        let via_obstacles = Self::parse_all_via_obs(&mut lines).map_err(at_line)?;
+        let nets = Self::parse_all_nets(&mut lines).map_err(at_line)?;
+        let (via_cost, direction_change_cost) =
+            Self::parse_extra_costs(&mut lines).map_err(at_line)?;
+        Self::validate_layers(layers, &obstacles, &via_obstacles, &nets)?;
+        let layer_stack =
+            Self::resolve_layer_stack(layers, &layer_specs, direction_change_cost as u32)?;
+        let via_rules = Self::resolve_via_rules(layers, &via_lines, via_cost as u32)?;
 
         Ok(Config {
-            grid_width: grid_width,
-            grid_height: grid_height,
-            obstacles: obstacles,
-            nets: nets,
-            via_cost: via_cost,
+            grid_width,
+            grid_height,
+            layers,
+            obstacles,
+            nets,
+            via_cost,
             nonpreferred_direction_cost: direction_change_cost,
+            layer_stack,
+            via_rules,
+            via_obstacles,
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::config::config::Config;
+    use crate::config::config::{Config, ViaLine};
+    use crate::{Blockage, LayerSpec, PreferredDirection, RouterError, ViaRules};
 
     #[test]
     fn test_parse_grid_dims_valid() {
@@ -169,10 +542,65 @@ mod tests {
         assert!(Config::parse_grid_dims("abcxdef").is_err());
     }
 
+    #[test]
+    fn test_parse_grid_dims_empty() {
+        assert_eq!(
+            Config::parse_grid_dims("0x5"),
+            Err("Grid dimensions have to be at least 1x1")
+        );
+        assert!(Config::parse_grid_dims("5x0").is_err());
+    }
+
     #[test]
     fn test_parse_obs_valid() {
-        assert_eq!(Config::parse_obs("OBS (1,12, 34)"), Ok((0, 12, 34)));
-        assert_eq!(Config::parse_obs("OBS (2, 1 , 2 )"), Ok((1, 1, 2)));
+        assert_eq!(
+            Config::parse_obs("OBS (1,12, 34)"),
+            Ok(Blockage::cell((0, 12, 34)))
+        );
+        assert_eq!(
+            Config::parse_obs("OBS (2, 1 , 2 )"),
+            Ok(Blockage::cell((1, 1, 2)))
+        );
+    }
+
+    #[test]
+    fn test_parse_obs_regions() {
+        assert_eq!(
+            Config::parse_obs("OBS (2, 8,9, 3,4)"),
+            Ok(Blockage {
+                name: None,
+                layer: Some(1),
+                rows: (3, 8),
+                cols: (4, 9),
+            })
+        );
+        assert_eq!(
+            Config::parse_obs("OBS (*,5,6)"),
+            Ok(Blockage {
+                name: None,
+                layer: None,
+                rows: (5, 5),
+                cols: (6, 6),
+            })
+        );
+        assert!(Config::parse_obs("OBS (1,2,3,4)").is_err());
+        assert!(Config::parse_obs("OBS (*,2,3,4,5,6)").is_err());
+    }
+
+    #[test]
+    fn test_parse_blockage() {
+        let regions =
+            Config::parse_blockage("BLOCKAGE ram0 (*,10,10,40,40) (1,41,10,45,12)").unwrap();
+        assert_eq!(regions.len(), 2);
+        assert!(regions
+            .iter()
+            .all(|region| region.name.as_deref() == Some("ram0")));
+        assert_eq!(regions[0].layer, None);
+        assert_eq!(regions[1].layer, Some(0));
+        assert_eq!(regions[1].rows, (41, 45));
+        assert!(Config::parse_blockage("BLOCKAGE (1,0,0)").is_err());
+        assert!(Config::parse_blockage("BLOCKAGE ram0 (1,0,0) 1,2,3").is_err());
+        assert!(Config::parse_blockage("BLOCKAGE ram0").is_err());
     }
 
     #[test]
@@ -181,6 +609,158 @@ mod tests {
         assert!(Config::parse_obs("OBS ()").is_err());
         assert!(Config::parse_obs("OBS (1,)").is_err());
         assert!(Config::parse_obs("OBS (a,b)").is_err());
+        assert!(Config::parse_obs("OBS (0,1,2)").is_err());
+    }
+
+    #[test]
+    fn test_parse_layers() {
+        let mut lines = vec!["layers 4", "OBS (1,1,2)"].into_iter().peekable();
+        assert_eq!(Config::parse_layers(&mut lines), Ok(4));
+        assert_eq!(lines.next(), Some("OBS (1,1,2)"));
+
+        let mut lines = vec!["OBS (1,1,2)"].into_iter().peekable();
+        assert_eq!(Config::parse_layers(&mut lines), Ok(2));
+
+        let mut lines = vec!["layers 0"].into_iter().peekable();
+        assert!(Config::parse_layers(&mut lines).is_err());
+    }
+
+    #[test]
+    fn test_parse_layer_spec() {
+        assert_eq!(
+            Config::parse_layer_spec("LAYER 2 vertical 7"),
+            Ok((1, PreferredDirection::Vertical, Some(7), None))
+        );
+        assert_eq!(
+            Config::parse_layer_spec("LAYER 3 none"),
+            Ok((2, PreferredDirection::None, None, None))
+        );
+        assert_eq!(
+            Config::parse_layer_spec("LAYER 1 horizontal 5 spacing 1"),
+            Ok((0, PreferredDirection::Horizontal, Some(5), Some(1)))
+        );
+        assert_eq!(
+            Config::parse_layer_spec("LAYER 1 horizontal spacing 2"),
+            Ok((0, PreferredDirection::Horizontal, None, Some(2)))
+        );
+        assert!(Config::parse_layer_spec("LAYER 0 vertical").is_err());
+        assert!(Config::parse_layer_spec("LAYER 1 diagonal").is_err());
+        assert!(Config::parse_layer_spec("LAYER 1 vertical x").is_err());
+        assert!(Config::parse_layer_spec("LAYER 1 vertical spacing").is_err());
+        assert!(Config::parse_layer_spec("LAYER 1 vertical 5 spacing 1 7").is_err());
+    }
+
+    #[test]
+    fn test_resolve_layer_stack() {
+        let specs = vec![
+            (1, PreferredDirection::None, None, None),
+            (2, PreferredDirection::Horizontal, Some(9), Some(1)),
+        ];
+        let stack = Config::resolve_layer_stack(3, &specs, 5).unwrap();
+        assert_eq!(
+            stack[0],
+            LayerSpec {
+                direction: PreferredDirection::Horizontal,
+                nonpreferred_cost: 5,
+                min_spacing: 0
+            }
+        );
+        assert_eq!(
+            stack[1],
+            LayerSpec {
+                direction: PreferredDirection::None,
+                nonpreferred_cost: 5,
+                min_spacing: 0
+            }
+        );
+        assert_eq!(
+            stack[2],
+            LayerSpec {
+                direction: PreferredDirection::Horizontal,
+                nonpreferred_cost: 9,
+                min_spacing: 1
+            }
+        );
+
+        assert!(Config::resolve_layer_stack(2, &specs, 5).is_err());
+    }
+
+    #[test]
+    fn test_parse_via_rule() {
+        assert_eq!(
+            Config::parse_via_rule("VIA 2 1 cost 15"),
+            Ok(ViaLine::Cost(0, 15))
+        );
+        assert_eq!(
+            Config::parse_via_rule("VIA stacking off"),
+            Ok(ViaLine::Stacking(false))
+        );
+        assert_eq!(
+            Config::parse_via_rule("VIA spacing 2"),
+            Ok(ViaLine::Spacing(2))
+        );
+        assert!(Config::parse_via_rule("VIA 1 3 cost 15").is_err());
+        assert!(Config::parse_via_rule("VIA 0 1 cost 15").is_err());
+        assert!(Config::parse_via_rule("VIA stacking maybe").is_err());
+        assert!(Config::parse_via_rule("VIA spacing").is_err());
+    }
+
+    #[test]
+    fn test_resolve_via_rules() {
+        let rules = vec![
+            ViaLine::Cost(1, 4),
+            ViaLine::Stacking(false),
+            ViaLine::Spacing(1),
+        ];
+        assert_eq!(
+            Config::resolve_via_rules(3, &rules, 10),
+            Ok(ViaRules {
+                stacking: false,
+                min_spacing: 1,
+                costs: vec![10, 4],
+            })
+        );
+        assert_eq!(
+            Config::resolve_via_rules(2, &[], 10),
+            Ok(ViaRules::uniform(2, 10))
+        );
+        assert!(Config::resolve_via_rules(2, &rules, 10).is_err());
+    }
+
+    #[test]
+    fn test_parse_via_obs() {
+        assert_eq!(
+            Config::parse_via_obs("VIAOBS (2,3,4)"),
+            Ok(Blockage::cell((1, 3, 4)))
+        );
+        assert_eq!(
+            Config::parse_via_obs("VIAOBS (*,3,4,5,6)").unwrap().layer,
+            None
+        );
+        assert!(Config::parse_via_obs("VIAOBS (0,3,4)").is_err());
+        assert!(Config::parse_via_obs("OBS (2,3,4)").is_err());
+
+        let mut lines = vec!["VIAOBS (1,1,2)", "net1 (1,2,3)"]
+            .into_iter()
+            .peekable();
+        assert_eq!(
+            Config::parse_all_via_obs(&mut lines),
+            Ok(vec![Blockage::cell((0, 1, 2))])
+        );
+        assert_eq!(lines.next(), Some("net1 (1,2,3)"));
+    }
+
+    #[test]
+    fn test_validate_layers() {
+        let net = Config::parse_net("net1 (1, 0, 0) (4, 2, 2)").unwrap();
+        let nets = std::slice::from_ref(&net);
+        let cell = |coord| [Blockage::cell(coord)];
+        assert!(Config::validate_layers(4, &cell((3, 1, 1)), &cell((0, 1, 1)), nets).is_ok());
+        assert!(Config::validate_layers(3, &[], &[], nets).is_err());
+        let shape = Config::parse_net("net2 (1,0,0)+(3-4,1,1)").unwrap();
+        assert!(Config::validate_layers(3, &[], &[], &[shape]).is_err());
+        assert!(Config::validate_layers(4, &cell((4, 1, 1)), &[], &[]).is_err());
+        assert!(Config::validate_layers(4, &[], &cell((4, 1, 1)), &[]).is_err());
     }
 
     #[test]
@@ -189,7 +769,27 @@ mod tests {
             .into_iter()
             .peekable();
         let result = Config::parse_all_obs(&mut lines).unwrap();
-        assert_eq!(result, vec![(0, 1, 2), (1, 3, 4)]);
+        assert_eq!(
+            result,
+            vec![Blockage::cell((0, 1, 2)), Blockage::cell((1, 3, 4))]
+        );
+    }
+
+    #[test]
+    fn test_parse_all_obs_with_named_blockage() {
+        let mut lines = vec![
+            "OBS (1,1,2)",
+            "BLOCKAGE pad (*,0,0,1,1)",
+            "OBS (2,3,4)",
+            "net1 (1,2,3)",
+        ]
+        .into_iter()
+        .peekable();
+        let result = Config::parse_all_obs(&mut lines).unwrap();
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0], Blockage::cell((0, 1, 2)));
+        assert_eq!(result[1].name.as_deref(), Some("pad"));
+        assert_eq!(result[2], Blockage::cell((1, 3, 4)));
     }
 
     #[test]
@@ -203,10 +803,51 @@ mod tests {
         assert_eq!(net.pins[0].coord.2, 20);
     }
 
+    #[test]
+    fn test_parse_net_priority() {
+        let net = Config::parse_net("net1 (1, 10, 20) (2, 30, 40) priority 3").unwrap();
+        assert_eq!(net.pins.len(), 2);
+        assert_eq!(net.priority, 3);
+        assert_eq!(Config::parse_net("net1 (1, 10, 20)").unwrap().priority, 0);
+        assert!(Config::parse_net("net1 (1, 10, 20) priority high").is_err());
+    }
+
     #[test]
     fn test_parse_net_invalid_format() {
         let line = "net1 1, 10, 20)";
         assert!(Config::parse_net(line).is_err());
+        assert!(Config::parse_net("net1 (0, 10, 20)").is_err());
+        assert!(Config::parse_net(" (1, 10, 20)").is_err());
+        assert!(Config::parse_net("my net (1, 10, 20)").is_err());
+    }
+
+    #[test]
+    fn test_parse_net_pin_shapes() {
+        let net = Config::parse_net("VDD (1-2, 0,0, 0,1)+(3,5,5) (1,9,9)").unwrap();
+        assert_eq!(net.pins.len(), 2);
+        assert_eq!(net.pins[0].coord, (0, 0, 0));
+        assert_eq!(
+            net.pins[0].shape,
+            vec![(0, 0, 1), (1, 0, 0), (1, 0, 1), (2, 5, 5)]
+        );
+        assert!(net.pins[1].shape.is_empty());
+
+        // overlapping parts do not repeat cells
+        let net = Config::parse_net("net1 (1,0,0,0,1)+(1,0,1)").unwrap();
+        assert_eq!(net.pins[0].shape, vec![(0, 0, 1)]);
+
+        assert!(Config::parse_net("net1 (1,0,0)+").is_err());
+        assert!(Config::parse_net("net1 (1,0,0,1)").is_err());
+        assert!(Config::parse_net("net1 (0-1,0,0)").is_err());
+        assert!(Config::parse_net("net1 (1-x,0,0)").is_err());
+    }
+
+    #[test]
+    fn test_parse_net_arbitrary_names() {
+        for name in ["VDD", "data[3]", "clk_core", "net300"] {
+            let net = Config::parse_net(&format!("{} (1, 2, 3) (1, 4, 5)", name)).unwrap();
+            assert_eq!(net._net_name, name);
+        }
     }
 
     #[test]
@@ -219,14 +860,38 @@ mod tests {
         assert_eq!(nets[0]._net_name, "net1");
     }
 
+    #[test]
+    fn test_parse_all_nets_with_any_names() {
+        let mut lines = vec!["net1 (1, 2, 3)", "VDD (2, 3, 4)", "via_cost 100"]
+            .into_iter()
+            .peekable();
+        let nets = Config::parse_all_nets(&mut lines).unwrap();
+        assert_eq!(nets.len(), 2);
+        assert_eq!(nets[1]._net_name, "VDD");
+    }
+
     #[test]
     fn test_parse_extra_costs_with_valid_lines() {
         let mut lines = vec!["via_cost 123", "direction_change_cost 456"].into_iter();
-        let (via, dir) = Config::parse_extra_costs(&mut lines);
+        let (via, dir) = Config::parse_extra_costs(&mut lines).unwrap();
         assert_eq!(via, 123);
         assert_eq!(dir, 456);
     }
 
+    #[test]
+    fn test_parse_extra_costs_rejects_negative() {
+        let mut lines = vec!["via_cost -1"].into_iter();
+        assert_eq!(
+            Config::parse_extra_costs(&mut lines),
+            Err("Costs cannot be negative")
+        );
+        let mut lines = vec!["via_cost 3", "nonpreferred_direction_cost -5"].into_iter();
+        assert_eq!(
+            Config::parse_extra_costs(&mut lines),
+            Err("Costs cannot be negative")
+        );
+    }
+
     #[test]
     fn test_build_full_config() {
         let input = "\
@@ -252,4 +917,81 @@ direction_change_cost 5";
 
         std::fs::remove_file(filename).unwrap();
     }
+
+    #[test]
+    fn test_build_config_with_via_rules() {
+        let input = "\
+10x20
+VIA stacking off
+OBS (1,1, 2)
+OBS (2,3, 4)
+VIAOBS (1,2,2)
+net1 (1, 10, 20) (2, 30, 40)
+net2 (1, 5, 5)
+via_cost 10
+direction_change_cost 5";
+
+        let filename = "test_input_via_rules.txt";
+        std::fs::write(filename, input).unwrap();
+        let config = Config::build(filename).unwrap();
+        std::fs::remove_file(filename).unwrap();
+
+        assert_eq!(config.layers, 2);
+        assert_eq!(config.obstacles.len(), 2);
+        assert!(!config.via_rules.stacking);
+        assert_eq!(config.via_rules.costs, vec![10]);
+        assert_eq!(config.via_obstacles, vec![Blockage::cell((0, 2, 2))]);
+    }
+
+    #[test]
+    fn test_build_reports_line_of_error() {
+        let input = "\
+10x20
+OBS (1,1, 2)
+OBS (1,1)
+net1 (1, 10, 20) (2, 30, 40)";
+
+        let filename = "test_input_error.txt";
+        std::fs::write(filename, input).unwrap();
+        let result = Config::build(filename);
+        std::fs::remove_file(filename).unwrap();
+
+        assert_eq!(
+            result.unwrap_err(),
+            RouterError::Parse {
+                line: 3,
+                message: "Invalid OBS format. Expected format like OBS (1,15,32), OBS (1,0,0,9,9) or OBS (*,15,32)"
+            }
+        );
+
+        let filename = "test_input_negative_cost.txt";
+        std::fs::write(filename, "3x3\nlayers 2\nnet1 (1,0,0) (2,2,2)\nvia_cost -1").unwrap();
+        let result = Config::build(filename);
+        std::fs::remove_file(filename).unwrap();
+        assert_eq!(
+            result.unwrap_err(),
+            RouterError::Parse {
+                line: 4,
+                message: "Costs cannot be negative"
+            }
+        );
+
+        // an empty grid has no cell an obstacle could be clipped to
+        let filename = "test_input_empty_grid.txt";
+        std::fs::write(filename, "0x5\nOBS (1,0,0,9,9)\nnet1 (1,0,0) (1,0,1)").unwrap();
+        let result = Config::build(filename);
+        std::fs::remove_file(filename).unwrap();
+        assert_eq!(
+            result.unwrap_err(),
+            RouterError::Parse {
+                line: 1,
+                message: "Grid dimensions have to be at least 1x1"
+            }
+        );
+
+        assert!(matches!(
+            Config::build("missing_input.txt"),
+            Err(RouterError::Io { .. })
+        ));
+    }
 }
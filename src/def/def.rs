@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+
+use crate::lef::lef::Tokens;
+use crate::RouterError;
+
+pub(crate) static DEFAULT_UNITS: i64 = 1000; // database units per micron when UNITS is missing
+
+// Sections the reader does not use, skipped up to their END
+static SKIPPED_SECTIONS: [&str; 11] = [
+    "VIAS",
+    "NONDEFAULTRULES",
+    "REGIONS",
+    "GROUPS",
+    "SPECIALNETS",
+    "PROPERTYDEFINITIONS",
+    "SCANCHAINS",
+    "FILLS",
+    "STYLES",
+    "SLOTS",
+    "BEGINEXT",
+];
+
+// DEF lengths are in database units, a rectangle is two corners
+pub type Rect = ((i64, i64), (i64, i64));
+
+// How a component or pin is placed, the F orientations are mirrored
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    North,
+    South,
+    East,
+    West,
+    FlippedNorth,
+    FlippedSouth,
+    FlippedEast,
+    FlippedWest,
+}
+
+impl Orientation {
+    fn parse(token: &str) -> Option<Orientation> {
+        Some(match token {
+            "N" => Orientation::North,
+            "S" => Orientation::South,
+            "E" => Orientation::East,
+            "W" => Orientation::West,
+            "FN" => Orientation::FlippedNorth,
+            "FS" => Orientation::FlippedSouth,
+            "FE" => Orientation::FlippedEast,
+            "FW" => Orientation::FlippedWest,
+            _ => return None,
+        })
+    }
+
+    // The DEF keyword, as parse reads it
+    pub fn name(self) -> &'static str {
+        match self {
+            Orientation::North => "N",
+            Orientation::South => "S",
+            Orientation::East => "E",
+            Orientation::West => "W",
+            Orientation::FlippedNorth => "FN",
+            Orientation::FlippedSouth => "FS",
+            Orientation::FlippedEast => "FE",
+            Orientation::FlippedWest => "FW",
+        }
+    }
+
+    // Where a point of a cell of the given size ends up once the cell is turned this way and
+    // its lower left corner put back at the origin
+    pub fn apply<T>(self, (x, y): (T, T), (width, height): (T, T)) -> (T, T)
+    where
+        T: Copy + std::ops::Sub<Output = T>,
+    {
+        match self {
+            Orientation::North => (x, y),
+            Orientation::South => (width - x, height - y),
+            Orientation::East => (y, width - x),
+            Orientation::West => (height - y, x),
+            Orientation::FlippedNorth => (width - x, y),
+            Orientation::FlippedSouth => (x, height - y),
+            Orientation::FlippedEast => (height - y, width - x),
+            Orientation::FlippedWest => (y, x),
+        }
+    }
+}
+
+// TRACKS X runs vertical tracks at X = start + i * step, TRACKS Y horizontal ones
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tracks {
+    pub along_x: bool,
+    pub start: i64,
+    pub count: usize,
+    pub step: i64,
+    pub layers: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub model: String,                                // the LEF macro
+    pub placement: Option<((i64, i64), Orientation)>, // None while unplaced
+}
+
+// A design pin from the PINS section
+#[derive(Debug, Clone, PartialEq)]
+pub struct IoPin {
+    pub layer: Option<String>,
+    pub rect: Option<Rect>, // around the placement point
+    pub placement: Option<((i64, i64), Orientation)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefNet {
+    pub name: String,
+    pub terminals: Vec<(String, String)>, // (component, pin), with PIN as the component of a design pin
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefBlockage {
+    pub layer: String,
+    pub component: Option<String>, // the component it belongs to, if any
+    pub rects: Vec<Rect>,
+}
+
+// The parts of a DEF design the router uses, everything else is skipped
+#[derive(Debug, Clone, Default)]
+pub struct Def {
+    pub name: String,
+    pub units: i64, // database units per micron
+    pub die: Option<Rect>,
+    pub tracks: Vec<Tracks>,
+    pub components: HashMap<String, Component>,
+    pub pins: HashMap<String, IoPin>,
+    pub nets: Vec<DefNet>, // in file order
+    pub blockages: Vec<DefBlockage>,
+}
+
+impl Def {
+    pub fn parse(path: &str, text: &str) -> Result<Def, RouterError> {
+        let mut tokens = Tokens::new(path, text);
+        let mut def = Def {
+            units: DEFAULT_UNITS,
+            ..Def::default()
+        };
+        while let Some(token) = tokens.next() {
+            match token {
+                "DESIGN" => {
+                    def.name = tokens.word()?.to_string();
+                    tokens.expect(";")?;
+                }
+                "UNITS" => {
+                    tokens.expect("DISTANCE")?;
+                    tokens.expect("MICRONS")?;
+                    def.units = tokens.number()?;
+                    tokens.expect(";")?;
+                }
+                "DIEAREA" => def.die = Some(Self::parse_die(&mut tokens)?),
+                "TRACKS" => def.tracks.push(Self::parse_tracks(&mut tokens)?),
+                "COMPONENTS" => Self::parse_section(&mut tokens, "COMPONENTS", |tokens| {
+                    let name = tokens.word()?.to_string();
+                    let component = Self::parse_component(tokens)?;
+                    def.components.insert(name, component);
+                    Ok(())
+                })?,
+                "PINS" => Self::parse_section(&mut tokens, "PINS", |tokens| {
+                    let name = tokens.word()?.to_string();
+                    let pin = Self::parse_pin(tokens)?;
+                    def.pins.insert(name, pin);
+                    Ok(())
+                })?,
+                "NETS" => Self::parse_section(&mut tokens, "NETS", |tokens| {
+                    def.nets.push(Self::parse_net(tokens)?);
+                    Ok(())
+                })?,
+                "BLOCKAGES" => Self::parse_section(&mut tokens, "BLOCKAGES", |tokens| {
+                    def.blockages.extend(Self::parse_blockage(tokens)?);
+                    Ok(())
+                })?,
+                "END" => {
+                    if tokens.next() == Some("DESIGN") {
+                        break;
+                    }
+                }
+                section if SKIPPED_SECTIONS.contains(&section) => tokens.skip_to_end(section),
+                _ => tokens.skip_statement(),
+            }
+        }
+        Ok(def)
+    }
+
+    // The bounding box of the points, DIEAREA may be a polygon
+    fn parse_die(tokens: &mut Tokens) -> Result<Rect, RouterError> {
+        let mut points = vec![];
+        while tokens.peek() == Some("(") {
+            points.push(tokens.point()?);
+        }
+        tokens.expect(";")?;
+        if points.len() < 2 {
+            return Err(tokens.error("DIEAREA needs at least two points"));
+        }
+        let xs = points.iter().map(|point| point.0);
+        let ys = points.iter().map(|point| point.1);
+        Ok((
+            (xs.clone().min().unwrap(), ys.clone().min().unwrap()),
+            (xs.max().unwrap(), ys.max().unwrap()),
+        ))
+    }
+
+    fn parse_tracks(tokens: &mut Tokens) -> Result<Tracks, RouterError> {
+        let along_x = match tokens.word()? {
+            "X" => true,
+            "Y" => false,
+            _ => return Err(tokens.error("TRACKS has to be X or Y")),
+        };
+        let start = tokens.number()?;
+        tokens.expect("DO")?;
+        let count = tokens.number()?;
+        tokens.expect("STEP")?;
+        let step: i64 = tokens.number()?;
+        if step <= 0 {
+            return Err(tokens.error("TRACKS STEP has to be positive"));
+        }
+        let mut layers = vec![];
+        loop {
+            match tokens.word()? {
+                ";" => break,
+                "LAYER" => {
+                    while !matches!(tokens.peek(), Some(";") | Some("MASK") | None) {
+                        layers.push(tokens.word()?.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(Tracks {
+            along_x,
+            start,
+            count,
+            step,
+            layers,
+        })
+    }
+
+    // SECTION count ; - entry ... ; ... END SECTION, every entry handed to parse_entry right
+    // after its '-'
+    fn parse_section<F>(
+        tokens: &mut Tokens,
+        section: &'static str,
+        mut parse_entry: F,
+    ) -> Result<(), RouterError>
+    where
+        F: FnMut(&mut Tokens) -> Result<(), RouterError>,
+    {
+        tokens.skip_statement(); // the entry count
+        loop {
+            match tokens.word()? {
+                "-" => parse_entry(tokens)?,
+                "END" => {
+                    tokens.expect(section)?;
+                    return Ok(());
+                }
+                _ => return Err(tokens.error("Expected '-' or END")),
+            }
+        }
+    }
+
+    // + PLACED ( x y ) orientation, or FIXED or COVER
+    fn parse_placement(tokens: &mut Tokens) -> Result<((i64, i64), Orientation), RouterError> {
+        let point = tokens.point()?;
+        let orientation = Orientation::parse(tokens.word()?)
+            .ok_or_else(|| tokens.error("Unknown orientation"))?;
+        Ok((point, orientation))
+    }
+
+    fn parse_component(tokens: &mut Tokens) -> Result<Component, RouterError> {
+        let mut component = Component {
+            model: tokens.word()?.to_string(),
+            placement: None,
+        };
+        loop {
+            match tokens.word()? {
+                ";" => return Ok(component),
+                "PLACED" | "FIXED" | "COVER" => {
+                    component.placement = Some(Self::parse_placement(tokens)?)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn parse_pin(tokens: &mut Tokens) -> Result<IoPin, RouterError> {
+        let mut pin = IoPin {
+            layer: None,
+            rect: None,
+            placement: None,
+        };
+        loop {
+            match tokens.word()? {
+                ";" => return Ok(pin),
+                "LAYER" => {
+                    pin.layer = Some(tokens.word()?.to_string());
+                    while tokens.peek() != Some("(") {
+                        tokens.word()?; // MASK or SPACING with their values
+                    }
+                    pin.rect = Some((tokens.point()?, tokens.point()?));
+                }
+                "PLACED" | "FIXED" | "COVER" => {
+                    pin.placement = Some(Self::parse_placement(tokens)?)
+                }
+                "POLYGON" => return Err(tokens.error("POLYGON pin shapes are not supported")),
+                _ => {}
+            }
+        }
+    }
+
+    // - name ( component pin ) ... followed by options up to the ';', existing routing included
+    fn parse_net(tokens: &mut Tokens) -> Result<DefNet, RouterError> {
+        let mut net = DefNet {
+            name: tokens.word()?.to_string(),
+            terminals: vec![],
+        };
+        while tokens.peek() == Some("(") {
+            tokens.next();
+            let component = tokens.word()?.to_string();
+            let pin = tokens.word()?.to_string();
+            while tokens.word()? != ")" {} // + SYNTHESIZED
+            net.terminals.push((component, pin));
+        }
+        tokens.skip_statement();
+        Ok(net)
+    }
+
+    // - LAYER name [+ COMPONENT name] ... RECT ( ) ( ) ... ; placement blockages are dropped
+    fn parse_blockage(tokens: &mut Tokens) -> Result<Option<DefBlockage>, RouterError> {
+        if tokens.peek() != Some("LAYER") {
+            tokens.skip_statement();
+            return Ok(None);
+        }
+        tokens.next();
+        let mut blockage = DefBlockage {
+            layer: tokens.word()?.to_string(),
+            component: None,
+            rects: vec![],
+        };
+        loop {
+            match tokens.word()? {
+                ";" => return Ok(Some(blockage)),
+                "COMPONENT" => blockage.component = Some(tokens.word()?.to_string()),
+                "RECT" => blockage.rects.push((tokens.point()?, tokens.point()?)),
+                "POLYGON" => return Err(tokens.error("POLYGON blockages are not supported")),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::def::def::{Def, Orientation, Tracks};
+    use crate::RouterError;
+
+    static DESIGN: &str = include_str!("../../test_cases/design1.def");
+
+    #[test]
+    fn test_parse_design() {
+        let def = Def::parse("design1.def", DESIGN).unwrap();
+        assert_eq!(def.name, "design1");
+        assert_eq!(def.units, 1000);
+        assert_eq!(def.die, Some(((0, 0), (2000, 1600))));
+        assert_eq!(
+            def.tracks[0],
+            Tracks {
+                along_x: true,
+                start: 100,
+                count: 10,
+                step: 200,
+                layers: vec!["metal1".to_string(), "metal2".to_string()],
+            }
+        );
+        assert_eq!(def.tracks.len(), 2);
+
+        assert_eq!(def.components["u1"].model, "INV");
+        assert_eq!(
+            def.components["u2"].placement,
+            Some(((1200, 400), Orientation::FlippedNorth))
+        );
+        assert_eq!(def.components["u3"].placement, None);
+
+        let clk = &def.pins["clk"];
+        assert_eq!(clk.layer.as_deref(), Some("metal2"));
+        assert_eq!(clk.rect, Some(((-50, 0), (50, 100))));
+        assert_eq!(clk.placement, Some(((300, 0), Orientation::North)));
+
+        let names: Vec<&str> = def.nets.iter().map(|net| net.name.as_str()).collect();
+        assert_eq!(names, vec!["clk", "n1", "VDD"]);
+        assert_eq!(
+            def.nets[0].terminals,
+            vec![
+                ("PIN".to_string(), "clk".to_string()),
+                ("u1".to_string(), "A".to_string())
+            ]
+        );
+
+        assert_eq!(def.blockages.len(), 3);
+        assert_eq!(def.blockages[0].layer, "metal1");
+        assert_eq!(def.blockages[0].component.as_deref(), Some("u3"));
+        assert_eq!(def.blockages[1].rects.len(), 2);
+    }
+
+    #[test]
+    fn test_orientations() {
+        let size = (6, 10);
+        let point = (1, 2);
+        assert_eq!(Orientation::North.apply(point, size), (1, 2));
+        assert_eq!(Orientation::South.apply(point, size), (5, 8));
+        assert_eq!(Orientation::FlippedNorth.apply(point, size), (5, 2));
+        assert_eq!(Orientation::FlippedSouth.apply(point, size), (1, 8));
+        // turned a quarter the cell is 10 wide and 6 high
+        assert_eq!(Orientation::West.apply(point, size), (8, 1));
+        assert_eq!(Orientation::East.apply(point, size), (2, 5));
+        assert_eq!(Orientation::FlippedWest.apply(point, size), (2, 1));
+        assert_eq!(Orientation::FlippedEast.apply(point, size), (8, 5));
+
+        for name in ["N", "S", "E", "W", "FN", "FS", "FE", "FW"] {
+            assert_eq!(Orientation::parse(name).unwrap().name(), name);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = Def::parse("bad.def", "DESIGN top ;\nTRACKS Z 0 DO 1 STEP 1 ;\n").unwrap_err();
+        assert_eq!(
+            err,
+            RouterError::DesignParse {
+                path: "bad.def".to_string(),
+                line: 2,
+                message: "TRACKS has to be X or Y"
+            }
+        );
+        assert!(Def::parse("bad.def", "COMPONENTS 1 ;\n- u1 INV + PLACED ( 0 0 ) Q ;\n").is_err());
+        assert!(Def::parse("bad.def", "NETS 1 ;\n- n1 ( u1 A ) ;\nEND PINS\n").is_err());
+
+        let polygon_pin = DESIGN.replace(
+            "+ LAYER metal2 ( -50 0 ) ( 50 100 )",
+            "+ POLYGON metal2 ( 0 0 ) ( 50 0 ) ( 0 50 )",
+        );
+        let polygon_blockage = DESIGN.replace(
+            "RECT ( 900 900 ) ( 1100 1300 )",
+            "POLYGON ( 900 900 ) ( 1100 900 ) ( 900 1300 )",
+        );
+        for (text, message) in [
+            (polygon_pin, "POLYGON pin shapes are not supported"),
+            (polygon_blockage, "POLYGON blockages are not supported"),
+        ] {
+            assert!(matches!(
+                Def::parse("design1.def", &text),
+                Err(RouterError::DesignParse { message: found, .. }) if found == message
+            ));
+        }
+    }
+}
@@ -0,0 +1,853 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::fs;
+
+use crate::config::config::{Config, DEFAULT_DIRECTION_CHANGE, DEFAULT_VIA_COST};
+use crate::def::def::{Component, Def, IoPin, Rect, DEFAULT_UNITS};
+use crate::lef::lef::{LayerKind, Lef, LefVia};
+use crate::{Blockage, Coord, LayerSpec, Net, Pin, RouterError, RoutingResult, ViaRules};
+
+// Where the grid sits in the database units of a DEF design. The grid's x (rows) follows the
+// design's Y axis and its y (columns) the X axis, so a HORIZONTAL layer prefers moving along a
+// row, as PreferredDirection::Horizontal does
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridMapping {
+    pub origin: (i64, i64), // X and Y of grid cell (x 0, y 0)
+    pub pitch: (i64, i64),  // track step along X and along Y
+}
+
+impl GridMapping {
+    // X and Y of the track crossing at grid position x,y
+    pub fn to_db(&self, (x, y): (usize, usize)) -> (i64, i64) {
+        (
+            self.origin.0 + y as i64 * self.pitch.0,
+            self.origin.1 + x as i64 * self.pitch.1,
+        )
+    }
+
+    // Grid position of the track crossing closest to X,Y, clamped to a grid of the given rows
+    // and columns
+    pub fn nearest(&self, (px, py): (i64, i64), (rows, cols): (usize, usize)) -> (usize, usize) {
+        let index = |v: i64, origin: i64, pitch: i64, count: usize| {
+            let steps = (v - origin + pitch / 2).div_euclid(pitch);
+            steps.clamp(0, count as i64 - 1) as usize
+        };
+        (
+            index(py, self.origin.1, self.pitch.1, rows),
+            index(px, self.origin.0, self.pitch.0, cols),
+        )
+    }
+
+    // First and last row and column of the track crossings inside the rectangle, None when it
+    // falls between tracks or off a grid of the given rows and columns
+    pub fn span(
+        &self,
+        ((x1, y1), (x2, y2)): Rect,
+        (rows, cols): (usize, usize),
+    ) -> Option<((usize, usize), (usize, usize))> {
+        let range = |low: i64, high: i64, origin: i64, pitch: i64, count: usize| {
+            let first = (low.min(high) - origin + pitch - 1)
+                .div_euclid(pitch)
+                .max(0);
+            let last = (low.max(high) - origin)
+                .div_euclid(pitch)
+                .min(count as i64 - 1);
+            (first <= last).then_some((first as usize, last as usize))
+        };
+        Some((
+            range(y1, y2, self.origin.1, self.pitch.1, rows)?,
+            range(x1, x2, self.origin.0, self.pitch.0, cols)?,
+        ))
+    }
+}
+
+// A DEF design read with its LEF library: the router input it describes and what it takes to
+// write the routes back in the design's own terms
+#[derive(Debug)]
+pub struct Design {
+    pub name: String,
+    pub config: Config,
+    pub mapping: GridMapping,
+    pub units: i64,                                        // database units per micron
+    pub layer_names: Vec<String>,                          // the routing layers, bottom up
+    pub via_names: Vec<Option<String>>, // via_names[l] is a LEF via between layers l and l + 1
+    pub cut_names: Vec<String>,         // cut_names[l] is the cut layer between layers l and l + 1
+    pub terminals: HashMap<String, Vec<(String, String)>>, // the DEF connections of each net, by name
+    pub components: Vec<(String, Component)>, // by name, written back so terminals resolve
+    pub pins: Vec<(String, IoPin)>,           // the design pins, by name
+}
+
+impl Design {
+    // A design for a maze read from the text format, its layers named metal1, metal2 ... and
+    // its nets connected to nothing but their routes
+    pub fn from_config(name: &str, config: Config, mapping: GridMapping) -> Design {
+        Design {
+            name: name.to_string(),
+            mapping,
+            units: DEFAULT_UNITS,
+            layer_names: (1..=config.layers).map(|l| format!("metal{}", l)).collect(),
+            via_names: vec![None; config.layers.saturating_sub(1)],
+            cut_names: (1..config.layers).map(|l| format!("via{}", l)).collect(),
+            terminals: HashMap::new(),
+            components: vec![],
+            pins: vec![],
+            config,
+        }
+    }
+
+    pub fn read(lef_path: &str, def_path: &str) -> Result<Design, RouterError> {
+        let read = |path: &str| {
+            fs::read_to_string(path).map_err(|err| RouterError::Io {
+                path: path.to_string(),
+                message: err.to_string(),
+            })
+        };
+        let lef = Lef::parse(lef_path, &read(lef_path)?)?;
+        let def = Def::parse(def_path, &read(def_path)?)?;
+        Design::build(&lef, &def)
+    }
+
+    // The grid is the finest TRACKS of each axis. Without TRACKS it falls back to the pitch of
+    // the first routing layer, half a pitch in from the corner of the die. Pins cover the track
+    // crossings inside their shapes, or the crossing nearest the shape if there is none.
+    // Blockages on a cut layer keep vias off the routing layer below it. Vias and wrong-way
+    // steps get the same default costs as the text format
+    pub fn build(lef: &Lef, def: &Def) -> Result<Design, RouterError> {
+        let layer_names: Vec<String> = lef.routing_layers().map(|l| l.name.clone()).collect();
+        if layer_names.is_empty() {
+            return Err(RouterError::NoRoutingGrid);
+        }
+        let routing_index: HashMap<&str, usize> = layer_names
+            .iter()
+            .enumerate()
+            .map(|(l, name)| (name.as_str(), l))
+            .collect();
+        let layer = |name: &str| {
+            routing_index
+                .get(name)
+                .copied()
+                .ok_or_else(|| unknown("layer", name))
+        };
+        let to_db = |microns: f64| (microns * def.units as f64).round() as i64;
+
+        let (mapping, rows, cols) = Self::grid(lef, def, to_db)?;
+        if rows > u16::MAX as usize || cols > u16::MAX as usize {
+            return Err(RouterError::GridTooLarge { rows, cols });
+        }
+
+        // cut layers sit between the routing layers listed around them
+        let mut below_cut: HashMap<&str, usize> = HashMap::new();
+        let mut routing_seen = 0;
+        for lef_layer in &lef.layers {
+            match lef_layer.kind {
+                LayerKind::Routing => routing_seen += 1,
+                LayerKind::Cut if routing_seen > 0 => {
+                    below_cut.insert(lef_layer.name.as_str(), routing_seen - 1);
+                }
+                _ => {}
+            }
+        }
+
+        let mut obstacles = vec![];
+        let mut via_obstacles = vec![];
+        for blockage in &def.blockages {
+            let (target, l) = match routing_index.get(blockage.layer.as_str()) {
+                Some(&l) => (&mut obstacles, l),
+                None => match below_cut.get(blockage.layer.as_str()) {
+                    Some(&l) => (&mut via_obstacles, l),
+                    None => return Err(unknown("layer", &blockage.layer)),
+                },
+            };
+            for &rect in &blockage.rects {
+                if let Some((rows, cols)) = mapping.span(rect, (rows, cols)) {
+                    target.push(Blockage {
+                        name: blockage.component.clone(),
+                        layer: Some(l),
+                        rows,
+                        cols,
+                    });
+                }
+            }
+        }
+
+        let mut nets = vec![];
+        for def_net in &def.nets {
+            let mut pins: Vec<Pin> = vec![];
+            for (component, pin) in &def_net.terminals {
+                if component == "*" {
+                    continue; // every component, as power nets connect
+                }
+                let mut shapes = Self::pin_shapes(lef, def, component, pin, to_db)?;
+                // ports may reach down to a cut layer, there is nothing to connect to there
+                shapes.retain(|(name, _)| {
+                    lef.layers
+                        .iter()
+                        .all(|l| l.name != *name || l.kind == LayerKind::Routing)
+                });
+                if shapes.is_empty() {
+                    return Err(unknown(
+                        "routing layer shape for",
+                        &format!("{} {}", component, pin),
+                    ));
+                }
+                let mut cells: Vec<Coord> = vec![];
+                for (layer_name, rect) in &shapes {
+                    let l = layer(layer_name)?;
+                    if let Some(((r0, r1), (c0, c1))) = mapping.span(*rect, (rows, cols)) {
+                        cells.extend((r0..=r1).flat_map(|r| (c0..=c1).map(move |c| (l, r, c))));
+                    }
+                }
+                if cells.is_empty() {
+                    let (layer_name, ((x1, y1), (x2, y2))) = &shapes[0];
+                    let center = ((x1 + x2) / 2, (y1 + y2) / 2);
+                    let (r, c) = mapping.nearest(center, (rows, cols));
+                    cells.push((layer(layer_name)?, r, c));
+                }
+                // port rectangles may overlap, and terminals that snap to the same grid cell
+                // join the pin they touch
+                let touching: Vec<usize> = (0..pins.len())
+                    .filter(|&i| pins[i].cells().any(|cell| cells.contains(cell)))
+                    .collect();
+                let mut merged: Vec<Coord> = vec![];
+                for &i in touching.iter().rev() {
+                    let pin = pins.remove(i);
+                    merged.splice(0..0, pin.cells().copied());
+                }
+                merged.extend(cells);
+                let mut seen = HashSet::new();
+                merged.retain(|&cell| seen.insert(cell));
+                let pin = Pin {
+                    coord: merged[0],
+                    shape: merged[1..].to_vec(),
+                };
+                pins.insert(touching.first().copied().unwrap_or(pins.len()), pin);
+            }
+            if !pins.is_empty() {
+                nets.push(Net {
+                    _net_name: def_net.name.clone(),
+                    pins,
+                    priority: 0,
+                });
+            }
+        }
+
+        let layer_stack = lef
+            .routing_layers()
+            .map(|lef_layer| LayerSpec {
+                direction: lef_layer.direction,
+                nonpreferred_cost: DEFAULT_DIRECTION_CHANGE as u32,
+                min_spacing: 0,
+            })
+            .collect();
+        let via_names = (0..layer_names.len().saturating_sub(1))
+            .map(|l| {
+                let joins = |via: &&LefVia| {
+                    let mut joined: Vec<usize> = via
+                        .layers
+                        .iter()
+                        .filter_map(|name| routing_index.get(name.as_str()).copied())
+                        .collect();
+                    joined.sort();
+                    joined == [l, l + 1]
+                };
+                let candidates: Vec<_> = lef.vias.iter().filter(joins).collect();
+                candidates
+                    .iter()
+                    .find(|via| via.default)
+                    .or(candidates.first())
+                    .map(|via| via.name.clone())
+            })
+            .collect();
+
+        let cut_names = (0..layer_names.len().saturating_sub(1))
+            .map(|l| {
+                below_cut
+                    .iter()
+                    .filter(|&(_, &below)| below == l)
+                    .map(|(&name, _)| name.to_string())
+                    .min()
+                    .unwrap_or_else(|| format!("via{}", l + 1))
+            })
+            .collect();
+        let mut components: Vec<(String, Component)> = def
+            .components
+            .iter()
+            .map(|(name, component)| (name.clone(), component.clone()))
+            .collect();
+        components.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut pins: Vec<(String, IoPin)> = def
+            .pins
+            .iter()
+            .map(|(name, pin)| (name.clone(), pin.clone()))
+            .collect();
+        pins.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Ok(Design {
+            name: def.name.clone(),
+            config: Config {
+                grid_width: rows as u16,
+                grid_height: cols as u16,
+                layers: layer_names.len(),
+                obstacles,
+                nets,
+                via_cost: DEFAULT_VIA_COST,
+                nonpreferred_direction_cost: DEFAULT_DIRECTION_CHANGE,
+                layer_stack,
+                via_rules: ViaRules::uniform(layer_names.len(), DEFAULT_VIA_COST as u32),
+                via_obstacles,
+            },
+            mapping,
+            units: def.units,
+            layer_names,
+            via_names,
+            cut_names,
+            terminals: def
+                .nets
+                .iter()
+                .map(|net| (net.name.clone(), net.terminals.clone()))
+                .collect(),
+            components,
+            pins,
+        })
+    }
+
+    // A LEF via between layers l and l + 1, or via1_2, via2_3 ... when the library has none
+    fn via_name(&self, l: usize) -> String {
+        self.via_names
+            .get(l)
+            .cloned()
+            .flatten()
+            .unwrap_or_else(|| format!("via{}_{}", l + 1, l + 2))
+    }
+
+    // The routes as a DEF NETS section in database units. Every segment is a wire path of its
+    // own and every via a one-point path on the lower of its layers. Nets that were only partly
+    // routed keep the wires they have, as in the other outputs, and a checker flags them open
+    pub fn routed_nets(&self, result: &RoutingResult) -> String {
+        let mut def = format!("NETS {} ;\n", result.nets.len());
+        for net in &result.nets {
+            write!(def, "- {}", net.name).unwrap();
+            for (component, pin) in self.terminals.get(&net.name).into_iter().flatten() {
+                write!(def, " ( {} {} )", component, pin).unwrap();
+            }
+            let mut keyword = "+ ROUTED";
+            for segment in &net.segments {
+                let (x1, y1) = self.mapping.to_db(segment.start);
+                let (x2, y2) = self.mapping.to_db(segment.end);
+                let end = if x1 == x2 {
+                    format!("( * {} )", y2)
+                } else {
+                    format!("( {} * )", x2)
+                };
+                write!(
+                    def,
+                    "\n  {} {} ( {} {} ) {}",
+                    keyword, self.layer_names[segment.layer], x1, y1, end
+                )
+                .unwrap();
+                keyword = "NEW";
+            }
+            for via in &net.vias {
+                let (x, y) = self.mapping.to_db((via.x, via.y));
+                write!(
+                    def,
+                    "\n  {} {} ( {} {} ) {}",
+                    keyword,
+                    self.layer_names[via.lower],
+                    x,
+                    y,
+                    self.via_name(via.lower)
+                )
+                .unwrap();
+                keyword = "NEW";
+            }
+            def.push_str(" ;\n");
+        }
+        def.push_str("END NETS\n");
+        def
+    }
+
+    // A DEF file of the routed nets with the components and pins their connections name, for
+    // viewers and checkers that load it over the LEF library. The die is the grid with half a
+    // pitch around it. Vias the library does not have are defined in a VIAS section
+    pub fn to_def(&self, result: &RoutingResult) -> String {
+        let (rows, cols) = (
+            self.config.grid_width as usize,
+            self.config.grid_height as usize,
+        );
+        let (x1, y1) = self.mapping.to_db((0, 0));
+        let (x2, y2) = self
+            .mapping
+            .to_db((rows.saturating_sub(1), cols.saturating_sub(1)));
+        let (hx, hy) = (self.mapping.pitch.0 / 2, self.mapping.pitch.1 / 2);
+        let mut def = format!(
+            "VERSION 5.8 ;\nDIVIDERCHAR \"/\" ;\nBUSBITCHARS \"[]\" ;\nDESIGN {} ;\nUNITS DISTANCE MICRONS {} ;\n\nDIEAREA ( {} {} ) ( {} {} ) ;\n\n",
+            self.name,
+            self.units,
+            x1 - hx,
+            y1 - hy,
+            x2 + hx,
+            y2 + hy
+        );
+        def.push_str(&self.generated_vias(result));
+        def.push_str(&self.placed_cells());
+        def.push_str(&self.routed_nets(result));
+        def.push_str("\nEND DESIGN\n");
+        def
+    }
+
+    // A VIAS section for the layer pairs routed nets change between without a LEF via, a square
+    // of a quarter pitch on both layers and the cut between them
+    fn generated_vias(&self, result: &RoutingResult) -> String {
+        let mut lower: Vec<usize> = result
+            .nets
+            .iter()
+            .flat_map(|net| net.vias.iter().map(|via| via.lower))
+            .filter(|&l| self.via_names.get(l).is_none_or(|name| name.is_none()))
+            .collect();
+        lower.sort();
+        lower.dedup();
+        if lower.is_empty() {
+            return String::new();
+        }
+        let half = (self.mapping.pitch.0.min(self.mapping.pitch.1) / 4).max(1);
+        let mut def = format!("VIAS {} ;\n", lower.len());
+        for l in lower {
+            write!(def, "- {}", self.via_name(l)).unwrap();
+            for layer in [
+                &self.layer_names[l],
+                &self.cut_names[l],
+                &self.layer_names[l + 1],
+            ] {
+                write!(
+                    def,
+                    "\n  + RECT {} ( {} {} ) ( {} {} )",
+                    layer, -half, -half, half, half
+                )
+                .unwrap();
+            }
+            def.push_str(" ;\n");
+        }
+        def.push_str("END VIAS\n\n");
+        def
+    }
+
+    // The COMPONENTS and PINS sections of the design that was read, empty for a text design
+    fn placed_cells(&self) -> String {
+        let mut def = String::new();
+        if !self.components.is_empty() {
+            writeln!(def, "COMPONENTS {} ;", self.components.len()).unwrap();
+            for (name, component) in &self.components {
+                write!(def, "- {} {}", name, component.model).unwrap();
+                match component.placement {
+                    Some(((x, y), orientation)) => {
+                        writeln!(def, " + PLACED ( {} {} ) {} ;", x, y, orientation.name()).unwrap()
+                    }
+                    None => def.push_str(" + UNPLACED ;\n"),
+                }
+            }
+            def.push_str("END COMPONENTS\n\n");
+        }
+        if !self.pins.is_empty() {
+            writeln!(def, "PINS {} ;", self.pins.len()).unwrap();
+            for (name, pin) in &self.pins {
+                let net = self
+                    .terminals
+                    .iter()
+                    .find(|(_, terminals)| terminals.iter().any(|(c, p)| c == "PIN" && p == name))
+                    .map_or(name, |(net, _)| net);
+                write!(def, "- {} + NET {}", name, net).unwrap();
+                if let (Some(layer), Some(((x1, y1), (x2, y2)))) = (&pin.layer, pin.rect) {
+                    write!(
+                        def,
+                        "\n  + LAYER {} ( {} {} ) ( {} {} )",
+                        layer, x1, y1, x2, y2
+                    )
+                    .unwrap();
+                }
+                if let Some(((x, y), orientation)) = pin.placement {
+                    write!(def, "\n  + PLACED ( {} {} ) {}", x, y, orientation.name()).unwrap();
+                }
+                def.push_str(" ;\n");
+            }
+            def.push_str("END PINS\n\n");
+        }
+        def
+    }
+
+    // The mapping with the number of rows and columns it covers
+    fn grid(
+        lef: &Lef,
+        def: &Def,
+        to_db: impl Fn(f64) -> i64,
+    ) -> Result<(GridMapping, usize, usize), RouterError> {
+        let finest = |along_x: bool| {
+            def.tracks
+                .iter()
+                .filter(|tracks| tracks.along_x == along_x && tracks.count > 0)
+                .min_by_key(|tracks| tracks.step)
+        };
+        let pitch = lef
+            .routing_layers()
+            .find_map(|layer| layer.pitch)
+            .map(|(x, y)| (to_db(x), to_db(y)))
+            .filter(|&(x, y)| x > 0 && y > 0);
+        // (start, count, step) of the tracks along one axis
+        let axis = |along_x: bool| match (finest(along_x), pitch, def.die) {
+            (Some(tracks), _, _) => Some((tracks.start, tracks.count, tracks.step)),
+            (None, Some(pitch), Some(((x1, y1), (x2, y2)))) => {
+                let (low, high, step) = if along_x {
+                    (x1, x2, pitch.0)
+                } else {
+                    (y1, y2, pitch.1)
+                };
+                let count = ((high - low) / step) as usize;
+                (count > 0).then_some((low + step / 2, count, step))
+            }
+            _ => None,
+        };
+        let (x_start, cols, x_step) = axis(true).ok_or(RouterError::NoRoutingGrid)?;
+        let (y_start, rows, y_step) = axis(false).ok_or(RouterError::NoRoutingGrid)?;
+        Ok((
+            GridMapping {
+                origin: (x_start, y_start),
+                pitch: (x_step, y_step),
+            },
+            rows,
+            cols,
+        ))
+    }
+
+    // The shapes of a net terminal in database units with their layer names, a design pin for
+    // PIN and otherwise the port rectangles of the component's macro
+    fn pin_shapes(
+        lef: &Lef,
+        def: &Def,
+        component: &str,
+        pin: &str,
+        to_db: impl Fn(f64) -> i64,
+    ) -> Result<Vec<(String, Rect)>, RouterError> {
+        if component == "PIN" {
+            let io = def.pins.get(pin).ok_or_else(|| unknown("pin", pin))?;
+            let (Some(layer), Some(((x1, y1), (x2, y2))), Some(((px, py), orientation))) =
+                (&io.layer, io.rect, io.placement)
+            else {
+                return Err(unknown("placed pin shape for", pin));
+            };
+            // a pin's rectangle is around its placement point, so it turns about that point
+            let (ax, ay) = orientation.apply((x1, y1), (0, 0));
+            let (bx, by) = orientation.apply((x2, y2), (0, 0));
+            let rect = (
+                (px + ax.min(bx), py + ay.min(by)),
+                (px + ax.max(bx), py + ay.max(by)),
+            );
+            return Ok(vec![(layer.clone(), rect)]);
+        }
+
+        let placed = def
+            .components
+            .get(component)
+            .ok_or_else(|| unknown("component", component))?;
+        let cell = lef
+            .macros
+            .get(&placed.model)
+            .ok_or_else(|| unknown("macro", &placed.model))?;
+        let ((px, py), orientation) = placed
+            .placement
+            .ok_or_else(|| unknown("placement for", component))?;
+        let ports = cell
+            .pins
+            .get(pin)
+            .filter(|ports| !ports.is_empty())
+            .ok_or_else(|| unknown("macro pin", &format!("{}/{}", placed.model, pin)))?;
+        let size = (to_db(cell.size.0), to_db(cell.size.1));
+        Ok(ports
+            .iter()
+            .map(|(layer, (x1, y1, x2, y2))| {
+                let a = orientation.apply((to_db(*x1), to_db(*y1)), size);
+                let b = orientation.apply((to_db(*x2), to_db(*y2)), size);
+                let rect = (
+                    (px + a.0.min(b.0), py + a.1.min(b.1)),
+                    (px + a.0.max(b.0), py + a.1.max(b.1)),
+                );
+                (layer.clone(), rect)
+            })
+            .collect())
+    }
+}
+
+fn unknown(kind: &'static str, name: &str) -> RouterError {
+    RouterError::UnknownName {
+        kind,
+        name: name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::config::Config;
+    use crate::def::def::Def;
+    use crate::design::design::{Design, GridMapping};
+    use crate::lef::lef::Lef;
+    use crate::{Blockage, Coord, Maze, NetStatus, PreferredDirection, RouterError, RoutingResult};
+
+    static LIBRARY: &str = include_str!("../../test_cases/design1.lef");
+    static DESIGN: &str = include_str!("../../test_cases/design1.def");
+
+    fn design(def: &str) -> Result<Design, RouterError> {
+        design_with(LIBRARY, def)
+    }
+
+    fn design_with(library: &str, def: &str) -> Result<Design, RouterError> {
+        let lef = Lef::parse("design1.lef", library).unwrap();
+        let def = Def::parse("design1.def", def).unwrap();
+        Design::build(&lef, &def)
+    }
+
+    fn pin_cells(design: &Design, net: usize) -> Vec<Vec<Coord>> {
+        let pins = design.config.nets[net].pins.iter();
+        pins.map(|pin| pin.cells().copied().collect()).collect()
+    }
+
+    #[test]
+    fn test_grid_mapping() {
+        let mapping = GridMapping {
+            origin: (100, 50),
+            pitch: (200, 100),
+        };
+        assert_eq!(mapping.to_db((2, 3)), (700, 250));
+        assert_eq!(mapping.nearest((690, 260), (8, 10)), (2, 3));
+        assert_eq!(mapping.nearest((-500, 99_999), (8, 10)), (7, 0));
+        assert_eq!(
+            mapping.span(((250, 0), (700, 160)), (8, 10)),
+            Some(((0, 1), (1, 3)))
+        );
+        assert_eq!(mapping.span(((310, 0), (490, 160)), (8, 10)), None);
+        assert_eq!(
+            mapping.span(((1500, 700), (9000, 9000)), (8, 10)),
+            Some(((7, 7), (7, 9)))
+        );
+    }
+
+    #[test]
+    fn test_build_design() {
+        let design = design(DESIGN).unwrap();
+        assert_eq!(design.name, "design1");
+        assert_eq!(
+            design.mapping,
+            GridMapping {
+                origin: (100, 100),
+                pitch: (200, 200),
+            }
+        );
+        assert_eq!(design.layer_names, vec!["metal1", "metal2"]);
+        assert_eq!(design.via_names, vec![Some("via12".to_string())]);
+
+        let config = &design.config;
+        assert_eq!((config.grid_width, config.grid_height), (8, 10));
+        assert_eq!(config.layers, 2);
+        assert_eq!(
+            config.layer_stack[0].direction,
+            PreferredDirection::Horizontal
+        );
+        assert_eq!(
+            config.layer_stack[1].direction,
+            PreferredDirection::Vertical
+        );
+
+        // the design pin, then u1/A placed as is, u1/Y on two tracks and u2/A mirrored
+        let pins: Vec<(&str, Vec<Vec<Coord>>)> = config
+            .nets
+            .iter()
+            .map(|net| {
+                let cells = net.pins.iter().map(|pin| pin.cells().copied().collect());
+                (net._net_name.as_str(), cells.collect())
+            })
+            .collect();
+        assert_eq!(
+            pins,
+            vec![
+                ("clk", vec![vec![(1, 0, 1)], vec![(0, 3, 1)]]),
+                ("n1", vec![vec![(0, 3, 3), (0, 4, 3)], vec![(0, 3, 8)]]),
+            ]
+        );
+
+        assert_eq!(
+            config.obstacles,
+            vec![
+                Blockage {
+                    name: Some("u3".to_string()),
+                    layer: Some(0),
+                    rows: (4, 6),
+                    cols: (4, 5),
+                },
+                Blockage {
+                    name: None,
+                    layer: Some(1),
+                    rows: (6, 7),
+                    cols: (0, 2),
+                },
+                Blockage {
+                    name: None,
+                    layer: Some(1),
+                    rows: (6, 7),
+                    cols: (7, 9),
+                },
+            ]
+        );
+        assert_eq!(config.via_obstacles, vec![Blockage::cell((0, 1, 6))]);
+    }
+
+    #[test]
+    fn test_build_design_subsets() {
+        // a port rectangle on the cut layer has nothing to connect to and is left out
+        let cut_port = LIBRARY.replace(
+            "        RECT 0.05 0.25 0.15 0.35 ;\n",
+            "        RECT 0.05 0.25 0.15 0.35 ;\n      LAYER via1 ;\n        RECT 0.05 0.25 0.15 0.35 ;\n",
+        );
+        let cut = design_with(&cut_port, DESIGN).unwrap();
+        assert_eq!(pin_cells(&cut, 0), vec![vec![(1, 0, 1)], vec![(0, 3, 1)]]);
+
+        // a design pin on the track crossing of u1/Y joins that pin of n1
+        let shared = DESIGN
+            .replace("PINS 1 ;", "PINS 2 ;")
+            .replace(
+                "END PINS",
+                "- tap + NET n1\n  + LAYER metal1 ( -50 -50 ) ( 50 50 )\n  + PLACED ( 700 700 ) N ;\nEND PINS",
+            )
+            .replace("- n1 ( u1 Y ) ( u2 A )", "- n1 ( u1 Y ) ( u2 A ) ( PIN tap )");
+        let shared = design(&shared).unwrap();
+        assert_eq!(
+            pin_cells(&shared, 1),
+            vec![vec![(0, 3, 3), (0, 4, 3)], vec![(0, 3, 8)]]
+        );
+    }
+
+    fn route(design: &Design) -> RoutingResult {
+        let mut maze = Maze::from_config(&design.config).unwrap();
+        maze.process_nets(&design.config.nets).unwrap()
+    }
+
+    #[test]
+    fn test_route_design() {
+        let result = route(&design(DESIGN).unwrap());
+        assert!(result
+            .nets
+            .iter()
+            .all(|net| net.status == NetStatus::Routed));
+    }
+
+    #[test]
+    fn test_write_routed_nets() {
+        let design = design(DESIGN).unwrap();
+        let result = route(&design);
+        let expected = "NETS 2 ;
+- clk ( PIN clk ) ( u1 A )
+  + ROUTED metal2 ( 300 100 ) ( * 700 )
+  NEW metal1 ( 300 700 ) via12 ;
+- n1 ( u1 Y ) ( u2 A )
+  + ROUTED metal1 ( 700 700 ) ( 1700 * ) ;
+END NETS
+";
+        assert_eq!(design.routed_nets(&result), expected);
+
+        // the whole file reads back with the same die, nets and the cells they connect to
+        let written = design.to_def(&result);
+        let def = Def::parse("routed.def", &written).unwrap();
+        let original = Def::parse("design1.def", DESIGN).unwrap();
+        assert_eq!(def.name, "design1");
+        assert_eq!(def.die, Some(((0, 0), (2000, 1600))));
+        assert_eq!(
+            def.nets[1].terminals,
+            vec![
+                ("u1".to_string(), "Y".to_string()),
+                ("u2".to_string(), "A".to_string())
+            ]
+        );
+        assert_eq!(def.components, original.components);
+        assert_eq!(def.pins, original.pins);
+        assert!(written.contains("- clk + NET clk\n"));
+        assert!(!written.contains("VIAS")); // via12 comes from the library
+
+        // a partly routed net keeps the wires it has, a failed one has none to write
+        let mut partial = result.clone();
+        partial.nets[1].status = NetStatus::Partial;
+        assert_eq!(design.routed_nets(&partial), expected);
+        let mut failed = result.clone();
+        failed.nets[1].status = NetStatus::Failed;
+        failed.nets[1].segments.clear();
+        assert!(design
+            .routed_nets(&failed)
+            .ends_with("- n1 ( u1 Y ) ( u2 A ) ;\nEND NETS\n"));
+    }
+
+    #[test]
+    fn test_write_text_design() {
+        let config = Config::build("test_cases/test_case1.txt").unwrap();
+        let mapping = GridMapping {
+            origin: (50, 50),
+            pitch: (100, 100),
+        };
+        let design = Design::from_config("test_case1", config, mapping);
+        let result = route(&design);
+        // a text design has no terminals and names its vias after the layers they join
+        let expected = "NETS 1 ;
+- net1
+  + ROUTED metal2 ( 50 50 ) ( * 350 )
+  NEW metal1 ( 50 350 ) ( 250 * )
+  NEW metal2 ( 250 350 ) ( * 50 )
+  NEW metal1 ( 250 50 ) ( 450 * )
+  NEW metal2 ( 450 50 ) ( * 450 )
+  NEW metal1 ( 50 50 ) via1_2
+  NEW metal1 ( 50 350 ) via1_2
+  NEW metal1 ( 250 350 ) via1_2
+  NEW metal1 ( 250 50 ) via1_2
+  NEW metal1 ( 450 50 ) via1_2
+  NEW metal1 ( 450 450 ) via1_2 ;
+END NETS
+";
+        assert_eq!(design.routed_nets(&result), expected);
+
+        // the vias it names are defined in the file itself
+        let written = design.to_def(&result);
+        let vias = "VIAS 1 ;
+- via1_2
+  + RECT metal1 ( -25 -25 ) ( 25 25 )
+  + RECT via1 ( -25 -25 ) ( 25 25 )
+  + RECT metal2 ( -25 -25 ) ( 25 25 ) ;
+END VIAS
+";
+        assert!(written.contains(vias));
+        assert!(!written.contains("COMPONENTS"));
+        assert!(!written.contains("PINS"));
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let unplaced = DESIGN.replace("( u2 A )", "( u3 A )");
+        assert_eq!(
+            design(&unplaced).unwrap_err(),
+            RouterError::UnknownName {
+                kind: "placement for",
+                name: "u3".to_string()
+            }
+        );
+        let missing = DESIGN.replace("LAYER metal2 RECT", "LAYER metal7 RECT");
+        assert_eq!(
+            design(&missing).unwrap_err(),
+            RouterError::UnknownName {
+                kind: "layer",
+                name: "metal7".to_string()
+            }
+        );
+
+        // without tracks the grid comes from the layer pitch, half a pitch in from the die
+        let untracked = DESIGN.replace("TRACKS", "# TRACKS");
+        let design = design(&untracked).unwrap();
+        assert_eq!(design.mapping.origin, (100, 100));
+        assert_eq!(
+            (design.config.grid_width, design.config.grid_height),
+            (8, 10)
+        );
+    }
+}
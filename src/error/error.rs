@@ -0,0 +1,176 @@
+use std::fmt;
+
+use crate::Coord;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouterError {
+    Usage(&'static str),
+    Io {
+        path: String,
+        message: String,
+    },
+    Output {
+        path: String,
+        message: String,
+    }, // an output file could not be written
+    EmptyInput,
+    Parse {
+        line: usize,
+        message: &'static str,
+    }, // line numbers start at 1
+    DesignParse {
+        path: String,
+        line: usize,
+        message: &'static str,
+    }, // in a LEF or DEF file
+    UnknownName {
+        kind: &'static str,
+        name: String,
+    }, // a DEF reference to something never defined
+    NoRoutingGrid, // neither TRACKS nor a routing layer PITCH to lay the grid on
+    GridTooLarge {
+        rows: usize,
+        cols: usize,
+    },
+    UnknownLayer {
+        layer: usize,
+        layers: usize,
+    }, // a LAYER line for a layer the design does not have
+    LayerStackSize {
+        expected: usize,
+        found: usize,
+    },
+    ViaCostCount {
+        expected: usize,
+        found: usize,
+    }, // one cost per pair of neighbouring layers
+    ObstacleOutOfRange {
+        coord: Coord,
+        layers: usize,
+    },
+    PinOutOfRange {
+        net: String,
+        coord: Coord,
+    },
+    OverlappingPins {
+        net: String,
+        other: String,
+        coord: Coord,
+    }, // a cell claimed by two pins, other may be the same net
+    InvalidNetName {
+        net: String,
+    }, // empty or containing whitespace
+    DuplicateNet {
+        net: String,
+    },
+    EmptyNet {
+        net: String,
+    },
+    UnexpectedCell {
+        net: String,
+        coord: Coord,
+    }, // the grid disagrees with the router's bookkeeping
+    InvalidRoute {
+        net: String,
+        coord: Coord,
+        message: &'static str,
+    }, // a route read back from a solution that does not fit the design
+}
+
+impl fmt::Display for RouterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // layers are printed 1-based, as in the input file
+        match self {
+            RouterError::Usage(msg) => write!(f, "{}", msg),
+            RouterError::Io { path, message } => write!(f, "Cannot read {}: {}", path, message),
+            RouterError::Output { path, message } => {
+                write!(f, "Cannot write {}: {}", path, message)
+            }
+            RouterError::EmptyInput => write!(f, "Input file is empty"),
+            RouterError::Parse { line, message } => write!(f, "Line {}: {}", line, message),
+            RouterError::DesignParse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}, line {}: {}", path, line, message),
+            RouterError::UnknownName { kind, name } => write!(f, "Unknown {} {}", kind, name),
+            RouterError::NoRoutingGrid => write!(
+                f,
+                "The design has no TRACKS and its routing layers no PITCH to place the grid on"
+            ),
+            RouterError::GridTooLarge { rows, cols } => write!(
+                f,
+                "A grid of {}x{} tracks is larger than the router supports",
+                rows, cols
+            ),
+            RouterError::UnknownLayer { layer, layers } => write!(
+                f,
+                "LAYER {} is outside the {} layers of the design",
+                layer + 1,
+                layers
+            ),
+            RouterError::LayerStackSize { expected, found } => write!(
+                f,
+                "Expected a layer stack of {} layers, got {}",
+                expected, found
+            ),
+            RouterError::ViaCostCount { expected, found } => write!(
+                f,
+                "Expected {} via costs, one per pair of neighbouring layers, got {}",
+                expected, found
+            ),
+            RouterError::ObstacleOutOfRange { coord, layers } => write!(
+                f,
+                "OBS ({},{},{}) is on layer {} but the design has {} layers",
+                coord.0 + 1,
+                coord.1,
+                coord.2,
+                coord.0 + 1,
+                layers
+            ),
+            RouterError::PinOutOfRange { net, coord } => write!(
+                f,
+                "Pin ({},{},{}) of {} is outside the grid",
+                coord.0 + 1,
+                coord.1,
+                coord.2,
+                net
+            ),
+            RouterError::OverlappingPins { net, other, coord } => write!(
+                f,
+                "Pin ({},{},{}) of {} overlaps a pin of {}",
+                coord.0 + 1,
+                coord.1,
+                coord.2,
+                net,
+                other
+            ),
+            RouterError::InvalidNetName { net } => write!(f, "Invalid net name '{}'", net),
+            RouterError::DuplicateNet { net } => write!(f, "Net {} is defined more than once", net),
+            RouterError::EmptyNet { net } => write!(f, "Net {} has no pins", net),
+            RouterError::UnexpectedCell { net, coord } => write!(
+                f,
+                "Routing {} found an unexpected cell at ({},{},{})",
+                net,
+                coord.0 + 1,
+                coord.1,
+                coord.2
+            ),
+            RouterError::InvalidRoute {
+                net,
+                coord,
+                message,
+            } => write!(
+                f,
+                "Route of {} at ({},{},{}): {}",
+                net,
+                coord.0 + 1,
+                coord.1,
+                coord.2,
+                message
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RouterError {}
@@ -0,0 +1,343 @@
+use crate::Coord;
+
+// Nets are numbered from 1 in input order, whatever their names
+pub type NetId = u32;
+
+// A cell is packed into a u32, the state in the low bits and the net id above it
+const STATE_BITS: u32 = 3;
+const STATE_MASK: u32 = (1 << STATE_BITS) - 1;
+pub const MAX_NET_ID: NetId = u32::MAX >> STATE_BITS;
+
+const FREE: u32 = 0;
+const BLOCKED: u32 = 1;
+const ROUTED: u32 = 2;
+const START: u32 = 3;
+const TARGET: u32 = 4;
+
+// The six moves out of a cell as (layer, row, column) offsets
+pub const MOVES: [(isize, isize, isize); 6] = [
+    (0, -1, 0), // up (vertical)
+    (0, 1, 0),  // down
+    (0, 0, -1), // left (horizontal)
+    (0, 0, 1),  // right
+    (-1, 0, 0), // layer down (via)
+    (1, 0, 0),  // layer up
+];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Cell {
+    Free,
+    Blocked,
+    Routed(NetId), // indicate which net
+    Start(NetId),  // part of the tree of the net being routed
+    Target,        // a cell the current search may stop at
+}
+
+impl Cell {
+    fn pack(self) -> u32 {
+        match self {
+            Cell::Free => FREE,
+            Cell::Blocked => BLOCKED,
+            Cell::Routed(net) => (net << STATE_BITS) | ROUTED,
+            Cell::Start(net) => (net << STATE_BITS) | START,
+            Cell::Target => TARGET,
+        }
+    }
+
+    fn unpack(bits: u32) -> Cell {
+        let net = bits >> STATE_BITS;
+        match bits & STATE_MASK {
+            BLOCKED => Cell::Blocked,
+            ROUTED => Cell::Routed(net),
+            START => Cell::Start(net),
+            TARGET => Cell::Target,
+            _ => Cell::Free,
+        }
+    }
+}
+
+// All layers in one flat buffer, a cell's linear id is ((layer * width) + row) * height + col
+#[derive(Clone, Debug)]
+pub struct Grid {
+    layers: usize,
+    width: usize,
+    height: usize,
+    cells: Vec<u32>,
+}
+
+impl Grid {
+    pub fn new(layers: usize, width: usize, height: usize) -> Self {
+        Grid {
+            layers,
+            width,
+            height,
+            cells: vec![Cell::Free.pack(); layers * width * height],
+        }
+    }
+
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Number of cells over all layers
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn contains(&self, (l, r, c): Coord) -> bool {
+        l < self.layers && r < self.width && c < self.height
+    }
+
+    pub fn index(&self, (l, r, c): Coord) -> usize {
+        (l * self.width + r) * self.height + c
+    }
+
+    pub fn coord(&self, index: usize) -> Coord {
+        let c = index % self.height;
+        let r = (index / self.height) % self.width;
+        (index / (self.height * self.width), r, c)
+    }
+
+    pub fn get(&self, coord: Coord) -> Cell {
+        Cell::unpack(self.cells[self.index(coord)])
+    }
+
+    pub fn set(&mut self, coord: Coord, cell: Cell) {
+        debug_assert!(!matches!(cell, Cell::Routed(net) | Cell::Start(net) if net > MAX_NET_ID));
+        let index = self.index(coord);
+        self.cells[index] = cell.pack();
+    }
+
+    // Sets every cell of a rectangle on one layer, one row slice at a time. The rectangle is
+    // clipped to the grid
+    pub fn fill(&mut self, l: usize, rows: (usize, usize), cols: (usize, usize), cell: Cell) {
+        if l >= self.layers || rows.0 >= self.width || cols.0 >= self.height || cols.0 > cols.1 {
+            return;
+        }
+        let last_col = cols.1.min(self.height - 1);
+        for r in rows.0..=rows.1.min(self.width - 1) {
+            let start = self.index((l, r, cols.0));
+            let end = self.index((l, r, last_col));
+            self.cells[start..=end].fill(cell.pack());
+        }
+    }
+
+    // The cell one move away, None past the edge of the grid
+    pub fn step(&self, (l, r, c): Coord, (dl, dr, dc): (isize, isize, isize)) -> Option<Coord> {
+        let next = (
+            l.checked_add_signed(dl)?,
+            r.checked_add_signed(dr)?,
+            c.checked_add_signed(dc)?,
+        );
+        self.contains(next).then_some(next)
+    }
+
+    // Every cell with its coordinate, layer by layer
+    pub fn iter(&self) -> impl Iterator<Item = (Coord, Cell)> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .map(|(index, &bits)| (self.coord(index), Cell::unpack(bits)))
+    }
+}
+
+const UNREACHED: u32 = u32::MAX;
+const NO_PARENT: u32 = 7; // the parent move takes the low 3 bits of a link, the tie count the rest
+const PARENT_BITS: u32 = 3;
+const MAX_TIE: u32 = u32::MAX >> PARENT_BITS;
+const PAGE_BITS: usize = 12; // cells per page, consecutive linear ids so a page is a strip of rows
+
+// Scratch state of one cell, 8 bytes
+#[derive(Clone, Copy, Debug)]
+struct Entry {
+    cost: u32,
+    link: u32, // (tie << PARENT_BITS) | parent move
+}
+
+const UNTOUCHED: Entry = Entry {
+    cost: UNREACHED,
+    link: NO_PARENT,
+};
+
+// Per-search bookkeeping, indexed by linear cell id: the best known cost of every cell, the
+// tie-break count of the path that reached it and the move (into MOVES) it came through.
+// Pages are only allocated once a search reaches one of their cells and go back to a pool on
+// reset, so the scratch grows with the area searches cover rather than with the grid
+#[derive(Clone, Debug)]
+pub struct Scratch {
+    pages: Vec<Option<Box<[Entry]>>>,
+    spare: Vec<Box<[Entry]>>, // cleared pages of earlier searches, used before allocating more
+    touched_pages: Vec<u32>,  // pages in use since the last reset, the only ones it has to clear
+    touched: usize,           // cells reached since the last reset
+}
+
+impl Scratch {
+    pub fn new(cells: usize) -> Self {
+        Scratch {
+            pages: vec![None; cells.div_ceil(1 << PAGE_BITS)],
+            spare: vec![],
+            touched_pages: vec![],
+            touched: 0,
+        }
+    }
+
+    fn entry(&self, index: usize) -> Entry {
+        self.pages[index >> PAGE_BITS]
+            .as_ref()
+            .map_or(UNTOUCHED, |page| page[index & ((1 << PAGE_BITS) - 1)])
+    }
+
+    // u32::MAX if the cell was not reached in this search
+    pub fn cost(&self, index: usize) -> u32 {
+        self.entry(index).cost
+    }
+
+    pub fn tie(&self, index: usize) -> u32 {
+        self.entry(index).link >> PARENT_BITS
+    }
+
+    pub fn parent_move(&self, index: usize) -> Option<usize> {
+        let parent = self.entry(index).link & NO_PARENT;
+        (parent != NO_PARENT).then_some(parent as usize)
+    }
+
+    // Number of cells reached since the last reset
+    pub fn touched(&self) -> usize {
+        self.touched
+    }
+
+    // A search starts from here at no cost
+    pub fn start(&mut self, index: usize) {
+        self.reach(index, 0, 0, NO_PARENT as usize);
+    }
+
+    pub fn reach(&mut self, index: usize, cost: u32, tie: u32, parent_move: usize) {
+        let page = index >> PAGE_BITS;
+        if self.pages[page].is_none() {
+            let fresh = self
+                .spare
+                .pop()
+                .unwrap_or_else(|| vec![UNTOUCHED; 1 << PAGE_BITS].into_boxed_slice());
+            self.pages[page] = Some(fresh);
+            self.touched_pages.push(page as u32);
+        }
+        let entry = &mut self.pages[page].as_mut().unwrap()[index & ((1 << PAGE_BITS) - 1)];
+        if entry.cost == UNREACHED {
+            self.touched += 1;
+        }
+        *entry = Entry {
+            cost,
+            link: (tie.min(MAX_TIE) << PARENT_BITS) | parent_move as u32,
+        };
+    }
+
+    pub fn reset(&mut self) {
+        for page in self.touched_pages.drain(..) {
+            let mut page = self.pages[page as usize].take().unwrap();
+            page.fill(UNTOUCHED);
+            self.spare.push(page);
+        }
+        self.touched = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::grid::grid::{Cell, Grid, Scratch, MAX_NET_ID, MAX_TIE, MOVES, PAGE_BITS};
+
+    #[test]
+    fn test_cells_round_trip() {
+        let mut grid = Grid::new(3, 4, 5);
+        let cells = [
+            Cell::Free,
+            Cell::Blocked,
+            Cell::Routed(1),
+            Cell::Routed(MAX_NET_ID),
+            Cell::Start(300),
+            Cell::Target,
+        ];
+        for (i, &cell) in cells.iter().enumerate() {
+            grid.set((2, 3, i % 5), cell);
+            assert_eq!(grid.get((2, 3, i % 5)), cell);
+        }
+        assert_eq!(grid.get((0, 0, 0)), Cell::Free);
+    }
+
+    #[test]
+    fn test_linear_ids() {
+        let grid = Grid::new(3, 4, 5);
+        assert_eq!(grid.len(), 60);
+        for index in 0..grid.len() {
+            assert_eq!(grid.index(grid.coord(index)), index);
+        }
+        assert_eq!(grid.coord(59), (2, 3, 4));
+        assert_eq!(grid.step((0, 0, 0), MOVES[0]), None);
+        assert_eq!(grid.step((0, 0, 0), MOVES[5]), Some((1, 0, 0)));
+        assert_eq!(grid.step((2, 3, 4), MOVES[5]), None);
+    }
+
+    #[test]
+    fn test_fill_clips_to_grid() {
+        let mut grid = Grid::new(2, 4, 5);
+        grid.fill(1, (2, 9), (3, 9), Cell::Blocked);
+        grid.fill(0, (4, 4), (0, 4), Cell::Blocked);
+        let blocked: Vec<_> = grid
+            .iter()
+            .filter(|&(_, cell)| cell == Cell::Blocked)
+            .map(|(coord, _)| coord)
+            .collect();
+        assert_eq!(blocked, vec![(1, 2, 3), (1, 2, 4), (1, 3, 3), (1, 3, 4)]);
+    }
+
+    #[test]
+    fn test_scratch_reset() {
+        let mut scratch = Scratch::new(10);
+        scratch.start(2);
+        scratch.reach(3, 9, 0, 0);
+        scratch.reach(3, 7, 2, 1);
+        assert_eq!(scratch.touched(), 2);
+        assert_eq!(scratch.cost(3), 7);
+        assert_eq!(scratch.tie(3), 2);
+        assert_eq!(scratch.parent_move(3), Some(1));
+        assert_eq!(scratch.parent_move(2), None);
+        scratch.reset();
+        assert_eq!(scratch.touched(), 0);
+        assert_eq!(scratch.cost(3), u32::MAX);
+        assert_eq!(scratch.tie(3), 0);
+        assert_eq!(scratch.cost(2), u32::MAX);
+        assert_eq!(scratch.parent_move(3), None);
+    }
+
+    #[test]
+    fn test_scratch_pages() {
+        // Cells on pages no search reached read as unreached, cleared pages are reused
+        let mut scratch = Scratch::new(3 << PAGE_BITS);
+        assert_eq!(scratch.cost(2 << PAGE_BITS), u32::MAX);
+        assert_eq!(scratch.parent_move(2 << PAGE_BITS), None);
+        scratch.start(5);
+        scratch.reach((2 << PAGE_BITS) + 1, 4, MAX_TIE + 9, 5);
+        assert_eq!(scratch.touched(), 2);
+        assert_eq!(scratch.cost((2 << PAGE_BITS) + 1), 4);
+        assert_eq!(scratch.tie((2 << PAGE_BITS) + 1), MAX_TIE);
+        assert_eq!(scratch.parent_move((2 << PAGE_BITS) + 1), Some(5));
+        assert_eq!(scratch.cost(1 << PAGE_BITS), u32::MAX);
+        scratch.reset();
+        assert_eq!(scratch.spare.len(), 2);
+        scratch.reach(1 << PAGE_BITS, 1, 0, 0);
+        assert_eq!(scratch.spare.len(), 1);
+        assert_eq!(scratch.cost((2 << PAGE_BITS) + 1), u32::MAX);
+        assert_eq!(scratch.cost((1 << PAGE_BITS) + 1), u32::MAX);
+    }
+}
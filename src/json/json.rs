@@ -0,0 +1,233 @@
+use crate::{
+    Blockage, Coord, LayerSpec, Maze, NetResult, PreferredDirection, RoutingResult,
+    SpacingViolation,
+};
+
+// The routing result as a JSON document, with the grid, layer stack and obstacles of the maze it
+// was taken from. Layers are numbered from 1 as in the input file, x is the row and y the column.
+// Top-level fields go on their own lines and every layer, obstacle and net on one line, so the
+// output stays readable and diffable
+pub fn to_json(maze: &Maze, result: &RoutingResult) -> String {
+    let fields = [
+        ("width", maze.width().to_string()),
+        ("height", maze.height().to_string()),
+        (
+            "layers",
+            lines(maze.layer_stack().iter().enumerate(), layer),
+        ),
+        ("obstacles", lines(maze.blockages(), obstacle)),
+        ("nets", lines(&result.nets, net)),
+        ("violations", lines(&result.violations, violation)),
+        ("total_cost", result.total_cost().to_string()),
+        ("expanded_cells", result.expanded_cells.to_string()),
+    ];
+    let body: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("  {}: {}", string(key), value))
+        .collect();
+    format!("{{\n{}\n}}\n", body.join(",\n"))
+}
+
+fn layer((l, spec): (usize, &LayerSpec)) -> String {
+    let direction = match spec.direction {
+        PreferredDirection::Horizontal => "horizontal",
+        PreferredDirection::Vertical => "vertical",
+        PreferredDirection::None => "none",
+    };
+    object(&[
+        ("layer", (l + 1).to_string()),
+        ("direction", string(direction)),
+        ("nonpreferred_cost", spec.nonpreferred_cost.to_string()),
+        ("min_spacing", spec.min_spacing.to_string()),
+    ])
+}
+
+// An obstacle that covers every layer has a null layer
+fn obstacle(blockage: &Blockage) -> String {
+    object(&[
+        (
+            "name",
+            blockage.name.as_deref().map_or("null".to_string(), string),
+        ),
+        (
+            "layer",
+            blockage
+                .layer
+                .map_or("null".to_string(), |l| (l + 1).to_string()),
+        ),
+        ("x", pair(blockage.rows)),
+        ("y", pair(blockage.cols)),
+    ])
+}
+
+fn net(net: &NetResult) -> String {
+    let segments = list(&net.segments, |segment| {
+        object(&[
+            ("layer", (segment.layer + 1).to_string()),
+            ("start", pair(segment.start)),
+            ("end", pair(segment.end)),
+        ])
+    });
+    let vias = list(&net.vias, |via| {
+        object(&[
+            ("x", via.x.to_string()),
+            ("y", via.y.to_string()),
+            ("lower", (via.lower + 1).to_string()),
+            ("upper", (via.upper + 1).to_string()),
+        ])
+    });
+    object(&[
+        ("name", string(&net.name)),
+        ("status", string(&format!("{:?}", net.status))),
+        (
+            "pins",
+            list(&net.pins, |pin| list(pin, |&cell| coord(cell))),
+        ),
+        ("cells", list(&net.cells, |&cell| coord(cell))),
+        ("segments", segments),
+        ("vias", vias),
+        ("cost", net.cost.to_string()),
+        ("wirelength", net.wirelength.to_string()),
+        (
+            "steiner_estimate",
+            net.steiner_estimate
+                .map_or("null".to_string(), |estimate| estimate.to_string()),
+        ),
+        (
+            "source_strategy",
+            string(&format!("{:?}", net.source_strategy)),
+        ),
+    ])
+}
+
+fn violation(violation: &SpacingViolation) -> String {
+    object(&[
+        ("layer", (violation.layer + 1).to_string()),
+        (
+            "nets",
+            format!(
+                "[{}, {}]",
+                string(&violation.nets.0),
+                string(&violation.nets.1)
+            ),
+        ),
+        (
+            "cells",
+            format!(
+                "[{}, {}]",
+                coord(violation.cells.0),
+                coord(violation.cells.1)
+            ),
+        ),
+    ])
+}
+
+fn coord((l, x, y): Coord) -> String {
+    format!("[{}, {}, {}]", l + 1, x, y)
+}
+
+fn pair((a, b): (usize, usize)) -> String {
+    format!("[{}, {}]", a, b)
+}
+
+fn object(fields: &[(&str, String)]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|(key, value)| format!("{}: {}", string(key), value))
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+fn list<T>(items: impl IntoIterator<Item = T>, item: impl Fn(T) -> String) -> String {
+    let items: Vec<String> = items.into_iter().map(item).collect();
+    format!("[{}]", items.join(", "))
+}
+
+// Like list, with each item on its own line under a top-level field
+fn lines<T>(items: impl IntoIterator<Item = T>, item: impl Fn(T) -> String) -> String {
+    let items: Vec<String> = items
+        .into_iter()
+        .map(|value| format!("    {}", item(value)))
+        .collect();
+    if items.is_empty() {
+        return "[]".to_string();
+    }
+    format!("[\n{}\n  ]", items.join(",\n"))
+}
+
+// A JSON string literal, net and blockage names may hold any character
+fn string(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => literal.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => literal.push(ch),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::json::json::{string, to_json};
+    use crate::{Blockage, Maze, Net, Pin};
+
+    #[test]
+    fn test_json_of_routed_maze() {
+        let mut maze = Maze::new(3, 4, 2, 10, 5);
+        maze.initialize_blockages(&[Blockage {
+            name: Some("ram".to_string()),
+            layer: None,
+            rows: (1, 1),
+            cols: (1, 2),
+        }]);
+        maze.initialize_obstacles(&vec![(1, 0, 3)]);
+        let nets = vec![Net {
+            _net_name: "data[0]".to_string(),
+            pins: vec![
+                Pin::at((0, 0, 0)),
+                Pin {
+                    coord: (0, 2, 3),
+                    shape: vec![(1, 2, 3)],
+                },
+            ],
+            priority: 0,
+        }];
+        let result = maze.process_nets(&nets).unwrap();
+
+        let expected = r#"{
+  "width": 3,
+  "height": 4,
+  "layers": [
+    {"layer": 1, "direction": "horizontal", "nonpreferred_cost": 5, "min_spacing": 0},
+    {"layer": 2, "direction": "vertical", "nonpreferred_cost": 5, "min_spacing": 0}
+  ],
+  "obstacles": [
+    {"name": "ram", "layer": null, "x": [1, 1], "y": [1, 2]},
+    {"name": null, "layer": 2, "x": [0, 0], "y": [3, 3]}
+  ],
+  "nets": [
+    {"name": "data[0]", "status": "Routed", "pins": [[[1, 0, 0]], [[1, 2, 3], [2, 2, 3]]], "cells": [[1, 0, 0], [1, 0, 1], [1, 0, 2], [1, 0, 3], [1, 1, 3], [1, 2, 3], [2, 2, 3]], "segments": [{"layer": 1, "start": [0, 0], "end": [0, 3]}, {"layer": 1, "start": [0, 3], "end": [2, 3]}], "vias": [], "cost": 15, "wirelength": 5, "steiner_estimate": null, "source_strategy": "FirstPin"}
+  ],
+  "violations": [],
+  "total_cost": 15,
+  "expanded_cells": 15
+}
+"#;
+        assert_eq!(to_json(&maze, &result), expected);
+    }
+
+    #[test]
+    fn test_strings_are_escaped() {
+        assert_eq!(string("clk"), "\"clk\"");
+        assert_eq!(string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(string("x\ny\u{1}"), "\"x\\ny\\u0001\"");
+    }
+}
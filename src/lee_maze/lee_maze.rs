@@ -1,33 +1,262 @@
-use core::net;
 use std::cmp::Reverse;
-use std::hash::Hash;
-use std::{
-    collections::{BinaryHeap, HashMap, HashSet},
-    u32::MAX,
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::grid::grid::{Cell, Grid, NetId, Scratch, MOVES};
+use crate::routing_result::routing_result::{
+    NetResult, NetStatus, RoutingResult, SpacingViolation,
+};
+use crate::steiner::steiner::rectilinear_steiner_tree;
+use crate::{
+    Blockage, Config, Coord, LayerSpec, Net, Pin, PreferredDirection, RouterError, ViaRules,
 };
 
-use crate::{Coord, Net, Pin};
+static DEFAULT_MAX_RIPUP_ITERATIONS: usize = 50;
+static RIPUP_PENALTY: u32 = 1000; // extra cost for crossing another net's wire while looking for blockers
+static HISTORY_INCREMENT: u32 = 1; // added to a cell's history cost for every extra net using it after an iteration
+static PRESENT_FACTOR_GROWTH: u32 = 2; // how fast sharing a cell gets more expensive between iterations
+static MAX_PRESENT_FACTOR: u32 = 1 << 16; // the growth stops here, long negotiations keep costs apart
+static MAX_CELL_NAME: usize = 6; // longest net name printed inside a grid cell, longer ones get ids
+
+type RoutedGroup = Result<(Maze, usize), RouterError>; // sub-maze of a parallel group and its rip-ups
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SearchAlgorithm {
+    Dijkstra, // uniform expansion around the sources
+    AStar,    // expansion guided by a lower bound on the cost to the closest remaining pin
+}
+
+// Which of several equally cheap paths a search returns. Costs are compared first, then the
+// policy's count (vias or bends, zero otherwise), then the queue prefers the entry closest to
+// its goal, i.e. the highest cost so far, and finally the policy's cell order. That makes the
+// order of expansion total, so routes depend only on the input and the options, never on how
+// the queue or the hash maps store their entries
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TieBreak {
+    Lexicographic, // the highest (layer, row, column) first
+    FewestVias,    // the path with fewer vias, then lexicographic
+    // Among equally cheap ways into a cell, the one with fewer bends so far, then lexicographic.
+    // Cells keep a single label, so this is greedy: a path that arrives with more bends but in
+    // a better direction is dropped, and the result is not always the path with fewest bends
+    FewestBends,
+    Random { seed: u64 }, // a seeded shuffle of the cells, different seeds sample different paths
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NetTopology {
+    Greedy,  // each search stops at whichever remaining pin is cheapest to reach
+    Steiner, // pins and Steiner points are connected in the order of a rectilinear Steiner tree
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SourceStrategy {
+    FirstPin,        // the first pin listed for the net
+    NearestCorner,   // the pin closest to a corner of the grid
+    Centroid,        // the pin closest to the centroid of all the net's pins
+    MostConstrained, // the pin with the fewest free neighbours
+    BestOfAll,       // route from every pin and keep the cheapest tree
+}
 
 #[derive(Clone, PartialEq, Debug)]
-pub enum Cell {
-    Free,
-    Blocked,
-    Routed(u8),     // indicate which net
-    Start(u8),      // indicate which net
-    Target(u32),    // cost
-    Candidate(u32), // cost
+pub enum NetOrder {
+    FileOrder,             // as listed in the input
+    HalfPerimeter,         // smallest pin bounding box (half perimeter) first
+    PinCount,              // fewest pins first
+    Priority,              // highest declared priority first
+    ShortestFirst,         // shortest Steiner tree estimate first
+    LongestFirst,          // longest Steiner tree estimate first
+    BestOf(Vec<NetOrder>), // route with each order, keep the fewest failed nets, then the lowest cost
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RoutingMode {
+    Sequential, // nets claim cells one after another, with rip-up and reroute
+    Negotiated { max_iterations: usize }, // nets share cells and negotiate them away (PathFinder)
+    Parallel { threads: usize, margin: usize }, // nets in disjoint regions are routed on separate threads, 0 threads uses every core
+}
+
+// What the router did with a single net, kept so the net can be ripped up later
+#[derive(Clone, Debug)]
+struct NetRoute {
+    name: String,
+    cells: Vec<Coord>, // the start pin first, then the cells in the order they were connected
+    pins: Vec<Vec<Coord>>, // the cells of every pin of the net
+    steps: Vec<(Coord, Coord)>, // every move of every path, in route order
+    vias: Vec<Coord>,
+    cost: u32,
+    wirelength: u32,               // steps within a layer, vias not included
+    steiner_estimate: Option<u32>, // wirelength of the Steiner tree the route followed
+    source_strategy: SourceStrategy,
+    pins_connected: usize, // pins joined to the source pin, the source included
+    routed: bool,          // false if some pins could not be connected
+    cropped: bool,         // on a sub-grid, a cheaper path may have left it
+}
+
+impl NetRoute {
+    // Pins with a cell among the cells of the net, both searches and loaded wires count them so
+    fn count_connected(net: &Net, cells: &[Coord]) -> usize {
+        let cells: HashSet<&Coord> = cells.iter().collect();
+        net.pins
+            .iter()
+            .filter(|pin| pin.cells().any(|cell| cells.contains(cell)))
+            .count()
+    }
+
+    fn status(&self) -> NetStatus {
+        if self.routed {
+            NetStatus::Routed
+        } else if self.pins_connected > 1 {
+            NetStatus::Partial
+        } else {
+            NetStatus::Failed
+        }
+    }
+
+    // Every via as its lower end, vias are stored as the two cells they join
+    fn via_cuts(&self) -> impl Iterator<Item = Coord> + '_ {
+        self.vias
+            .chunks(2)
+            .map(|ends| (ends[0].0.min(ends[1].0), ends[0].1, ends[0].2))
+    }
+
+    // The same route on a grid whose first cell sits at origin
+    fn shifted(mut self, origin: (usize, usize)) -> NetRoute {
+        let shift = |(l, r, c): Coord| (l, r + origin.0, c + origin.1);
+        for cell in self
+            .cells
+            .iter_mut()
+            .chain(self.vias.iter_mut())
+            .chain(self.pins.iter_mut().flatten())
+        {
+            *cell = shift(*cell);
+        }
+        for step in &mut self.steps {
+            *step = (shift(step.0), shift(step.1));
+        }
+        self
+    }
+}
+
+// The routes and what they leave outside the grid, enough to undo a routing attempt
+#[derive(Clone)]
+struct RoutedState {
+    routes: HashMap<usize, NetRoute>,
+    vias: HashSet<Coord>,
+    original_sources: HashSet<Coord>,
+    expanded_cells: usize,
+}
+
+// A stop on the way through a Steiner tree, reached once any of its cells is
+struct Waypoint {
+    cells: Vec<Coord>,
+    pin: bool, // Steiner points may be skipped, pins may not
+}
+
+// Per-cell bookkeeping of the negotiated congestion mode
+#[derive(Clone, Debug, Default)]
+struct Congestion {
+    occupancy: HashMap<Coord, u32>, // number of nets currently using the cell
+    history: HashMap<Coord, u32>,   // grows every iteration the cell stays overused
+    present_factor: u32,
+}
+
+impl Congestion {
+    // Cost of entering a cell, on top of the base via/direction cost
+    fn cost(&self, cell: &Coord, base_cost: u32) -> u32 {
+        let occupancy = self.occupancy.get(cell).copied().unwrap_or(0);
+        let history = self.history.get(cell).copied().unwrap_or(0);
+        // saturates rather than wraps once a cell has been fought over for long enough
+        base_cost.saturating_add(history).saturating_mul(
+            self.present_factor
+                .saturating_mul(occupancy)
+                .saturating_add(1),
+        )
+    }
+
+    fn overused_cells(&self) -> Vec<(Coord, u32)> {
+        self.occupancy
+            .iter()
+            .filter(|(_, &occupancy)| occupancy > 1)
+            .map(|(&cell, &occupancy)| (cell, occupancy))
+            .collect()
+    }
+}
+
+// Rows and columns a search may enter, both ends inclusive
+#[derive(Clone, Copy, Debug)]
+struct Window {
+    rows: (usize, usize),
+    cols: (usize, usize),
+}
+
+impl Window {
+    fn contains(&self, (_, r, c): Coord) -> bool {
+        (self.rows.0..=self.rows.1).contains(&r) && (self.cols.0..=self.cols.1).contains(&c)
+    }
+
+    fn overlaps(&self, other: &Window) -> bool {
+        self.rows.0 <= other.rows.1
+            && other.rows.0 <= self.rows.1
+            && self.cols.0 <= other.cols.1
+            && other.cols.0 <= self.cols.1
+    }
+
+    // The window with gap more cells on every side, not clipped to the grid
+    fn grown(&self, gap: usize) -> Window {
+        Window {
+            rows: (self.rows.0.saturating_sub(gap), self.rows.1 + gap),
+            cols: (self.cols.0.saturating_sub(gap), self.cols.1 + gap),
+        }
+    }
+
+    fn union(&self, other: &Window) -> Window {
+        Window {
+            rows: (self.rows.0.min(other.rows.0), self.rows.1.max(other.rows.1)),
+            cols: (self.cols.0.min(other.cols.0), self.cols.1.max(other.cols.1)),
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct Maze {
-    grid: Vec<Vec<Vec<Cell>>>, // [layer][row][col]
+    grid: Grid,
+    scratch: Scratch, // costs and parents of the current search
     start_cords: Vec<Coord>,
-    via_cost: u32,
     width: usize,
     height: usize,
-    nonpreferred_direction_cost: u32,
-    vias: HashSet<Coord>,
+    layer_stack: Vec<LayerSpec>,
+    via_rules: ViaRules,
+    via_blockages: HashSet<Coord>, // cells wires may use but no via may land on
+    blockages: Vec<Blockage>,      // every obstacle as it was given, named or not
+    vias: HashSet<Coord>,          // both ends of every via
     original_sources: HashSet<Coord>,
-    current_net_processed: u8,
+    current_net_processed: NetId,
+    current_vias: Vec<Coord>,
+    current_steps: Vec<(Coord, Coord)>,
+    pins: HashSet<Coord>, // pins of every net, other nets may never route over them
+    routes: HashMap<usize, NetRoute>, // keyed by the net's position in the input
+    penalized: HashSet<Coord>, // cells that cost RIPUP_PENALTY more to enter
+    max_ripup_iterations: usize,
+    mode: RoutingMode,
+    congestion: Option<Congestion>, // only while negotiating
+    search: SearchAlgorithm,
+    targets: Vec<Coord>,           // cells where the current search may stop
+    current_pins: Vec<Vec<Coord>>, // shapes of the current net's pins, reaching one cell joins them all
+    expanded_cells: usize,
+    topology: NetTopology,
+    current_wirelength: u32,
+    current_cropped: bool, // a search of the current net may have missed a cheaper path off the sub-grid
+    source_strategy: SourceStrategy,
+    net_order: NetOrder,
+    net_ids: HashMap<String, NetId>,
+    net_names: Vec<String>,       // net_names[id - 1] is the name of net id
+    search_margin: Option<usize>, // searches stay within the net's bounding box grown by this
+    window: Option<Window>,       // only while searching with a margin
+    tie_break: TieBreak,
+    origin: (usize, usize), // row and column of the first cell in the full design, sub-grids of parallel routing are offset
+    die: (usize, usize),    // width and height of the full design
+    free_beyond: HashSet<Coord>, // free cells of the full design next to a sub-grid, in the full design's coordinates
 }
 
 impl Maze {
@@ -40,259 +269,2718 @@ impl Maze {
         nonpreferred_direction_cost: u32,
     ) -> Self {
         Maze {
-            grid: vec![vec![vec![Cell::Free; height]; width]; layers],
+            grid: Grid::new(layers, width, height),
+            scratch: Scratch::new(layers * width * height),
             start_cords: vec![],
-            via_cost,
             width,
             height,
-            nonpreferred_direction_cost,
+            layer_stack: LayerSpec::alternating(layers, nonpreferred_direction_cost),
+            via_rules: ViaRules::uniform(layers, via_cost),
+            via_blockages: HashSet::new(),
+            blockages: vec![],
             vias: HashSet::new(),
             original_sources: HashSet::new(),
             current_net_processed: 1, // temporary
+            current_vias: vec![],
+            current_steps: vec![],
+            pins: HashSet::new(),
+            routes: HashMap::new(),
+            penalized: HashSet::new(),
+            max_ripup_iterations: DEFAULT_MAX_RIPUP_ITERATIONS,
+            mode: RoutingMode::Sequential,
+            congestion: None,
+            search: SearchAlgorithm::Dijkstra,
+            targets: vec![],
+            current_pins: vec![],
+            expanded_cells: 0,
+            topology: NetTopology::Greedy,
+            current_wirelength: 0,
+            current_cropped: false,
+            source_strategy: SourceStrategy::FirstPin,
+            net_order: NetOrder::FileOrder,
+            net_ids: HashMap::new(),
+            net_names: vec![],
+            search_margin: None,
+            window: None,
+            tie_break: TieBreak::Lexicographic,
+            origin: (0, 0),
+            die: (width, height),
+            free_beyond: HashSet::new(),
+        }
+    }
+
+    // The maze of a parsed design: its size, layer stack, via rules and blockages
+    pub fn from_config(config: &Config) -> Result<Self, RouterError> {
+        let mut maze = Maze::new(
+            config.grid_width as usize,
+            config.grid_height as usize,
+            config.layers,
+            config.via_cost as u32,
+            config.nonpreferred_direction_cost as u32,
+        );
+        maze.set_layer_stack(config.layer_stack.clone())?;
+        maze.set_via_rules(config.via_rules.clone())?;
+        maze.initialize_blockages(&config.obstacles);
+        maze.initialize_via_blockages(&config.via_obstacles);
+        Ok(maze)
+    }
+
+    pub fn set_net_order(&mut self, order: NetOrder) {
+        self.net_order = order;
+    }
+
+    pub fn set_source_strategy(&mut self, strategy: SourceStrategy) {
+        self.source_strategy = strategy;
+    }
+
+    pub fn set_net_topology(&mut self, topology: NetTopology) {
+        self.topology = topology;
+    }
+
+    pub fn set_search_algorithm(&mut self, search: SearchAlgorithm) {
+        self.search = search;
+    }
+
+    pub fn set_tie_break(&mut self, tie_break: TieBreak) {
+        self.tie_break = tie_break;
+    }
+
+    // Keeps every search within the net's bounding box grown by margin cells, the margin is
+    // widened whenever a search fails inside it. None searches the whole grid
+    pub fn set_search_margin(&mut self, margin: Option<usize>) {
+        self.search_margin = margin;
+    }
+
+    // Preferred direction and penalty of every layer, replaces the alternating default
+    pub fn set_layer_stack(&mut self, layer_stack: Vec<LayerSpec>) -> Result<(), RouterError> {
+        if layer_stack.len() != self.grid.layers() {
+            return Err(RouterError::LayerStackSize {
+                expected: self.grid.layers(),
+                found: layer_stack.len(),
+            });
+        }
+        self.layer_stack = layer_stack;
+        Ok(())
+    }
+
+    // Stacking, spacing and per-layer-pair costs of vias, replaces the single via cost
+    pub fn set_via_rules(&mut self, via_rules: ViaRules) -> Result<(), RouterError> {
+        let expected = self.grid.layers().saturating_sub(1);
+        if via_rules.costs.len() != expected {
+            return Err(RouterError::ViaCostCount {
+                expected,
+                found: via_rules.costs.len(),
+            });
         }
+        self.via_rules = via_rules;
+        Ok(())
+    }
+
+    pub fn set_routing_mode(&mut self, mode: RoutingMode) {
+        self.mode = mode;
+    }
+
+    // Upper bound on how many times nets get ripped up to make room for a net that failed
+    pub fn set_max_ripup_iterations(&mut self, iterations: usize) {
+        self.max_ripup_iterations = iterations;
+    }
+
+    // Number of rows (x)
+    pub fn width(&self) -> usize {
+        self.width
     }
 
-    // Check if a coordinate is within bounds
-    fn is_valid(&self, l: isize, r: isize, c: isize) -> bool {
-        l >= 0 && r >= 0 && c >= 0 &&
-        (l as usize) < self.grid.len() &&
-        (r as usize) < self.width &&
-        (c as usize) < self.height &&
+    // Number of columns (y)
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn layer_stack(&self) -> &[LayerSpec] {
+        &self.layer_stack
+    }
+
+    // Obstacles in the order they were added, single cells included
+    pub fn blockages(&self) -> &[Blockage] {
+        &self.blockages
+    }
+
+    // What the grid holds at a cell, the state print_layers_side_by_side draws
+    pub fn cell(&self, coord: Coord) -> Cell {
+        self.grid.get(coord)
+    }
+
+    // Whether a via starts or ends at the cell
+    pub fn is_via(&self, coord: Coord) -> bool {
+        self.vias.contains(&coord)
+    }
+
+    // Check if a coordinate is within bounds and may be entered
+    fn is_valid(&self, coord: Coord) -> bool {
         // Skip Blocked, Routed, and Start cells
-        !matches!(self.grid[l as usize][r as usize][c as usize], Cell::Blocked | Cell::Routed(_) | Cell::Start(_))
+        self.grid.contains(coord)
+            && self.window.is_none_or(|window| window.contains(coord))
+            && match self.grid.get(coord) {
+                Cell::Free => self.keeps_spacing(coord, self.current_net_processed),
+                Cell::Target => true, // pins of the net itself may sit next to anything
+                _ => false,
+            }
     }
 
-    fn neighbors(&self, l: usize, r: usize, c: usize) -> Vec<(Coord, u32)> {
-        let deltas = vec![
-            (0, -1, 0), // up (vertical)
-            (0, 1, 0),  // down
-            (0, 0, -1), // left (horizontal)
-            (0, 0, 1),  // right
-            (-1, 0, 0), // layer down (via)
-            (1, 0, 0),  // layer up
-        ];
+    // Whether a wire of the net at this cell stays at least its layer's minimum spacing away from
+    // every other net's cells on the same layer
+    fn keeps_spacing(&self, coord: Coord, net: NetId) -> bool {
+        self.spacing_zone(coord)
+            .all(|near| match self.grid.get(near) {
+                Cell::Routed(other) | Cell::Start(other) => other == net,
+                _ => true,
+            })
+    }
+
+    // Cells of the same layer no other net may use while a wire sits at this one, the cell itself
+    // included. Empty when the layer has no spacing rule
+    fn spacing_zone(&self, (l, r, c): Coord) -> impl Iterator<Item = Coord> {
+        let spacing = self.layer_stack[l].min_spacing;
+        let rows = r.saturating_sub(spacing)..=(r + spacing).min(self.grid.width() - 1);
+        let cols = c.saturating_sub(spacing)..=(c + spacing).min(self.grid.height() - 1);
+        rows.filter(move |_| spacing > 0)
+            .flat_map(move |x| cols.clone().map(move |y| (l, x, y)))
+    }
 
+    // Cells one move away that may be entered, with the move (into MOVES) and its cost
+    fn neighbors(&self, cell: Coord) -> Vec<(usize, Coord, u32)> {
         let mut result = Vec::new();
-        for (dl, dr, dc) in deltas {
-            let nl = l as isize + dl;
-            let nr = r as isize + dr;
-            let nc = c as isize + dc;
-
-            if self.is_valid(nl, nr, nc) {
-                let nl = nl as usize;
-                let nr = nr as usize;
-                let nc = nc as usize;
-                // Base cost: 1 for horizontal/vertical, via_cost for via
-                let mut cost = if dl != 0 { self.via_cost } else { 1 };
-
-                // Directional penalties
-                if l % 2 == 0 && dr != 0 {
-                    cost += self.nonpreferred_direction_cost;
-                } // Vertical cost on even layers
-                if l % 2 != 0 && dc != 0 {
-                    cost += self.nonpreferred_direction_cost;
-                } // Horizontal cost on odd layers
-
-                match self.grid[nl][nr][nc] {
-                    Cell::Free | Cell::Candidate(_) | Cell::Target(_) => {
-                        result.push(((nl, nr, nc), cost))
-                    }
-                    _ => {}
-                }
+        for (mv, &offset) in MOVES.iter().enumerate() {
+            let (dl, dr, _) = offset;
+            let Some(next) = self.grid.step(cell, offset) else {
+                continue;
+            };
+            if !self.is_valid(next) || (dl != 0 && !self.via_allowed(cell, next)) {
+                continue;
+            }
+            // Base cost: 1 for horizontal/vertical, the cost of the layer pair for a via
+            let mut cost = if dl != 0 {
+                self.via_rules.costs[cell.0.min(next.0)]
+            } else {
+                self.step_cost(cell.0, dr != 0)
+            };
+
+            // Crossing a wire that may be ripped up
+            if self.penalized.contains(&next) {
+                cost += RIPUP_PENALTY;
             }
+            // Sharing a cell with other nets while negotiating
+            if let Some(congestion) = &self.congestion {
+                cost = congestion.cost(&next, cost);
+            }
+            result.push((mv, next, cost));
         }
         result
     }
 
-    // Dijkstra to find the path between start and target
-    fn dijkstra(&mut self) {
-        let mut queue = BinaryHeap::new(); // Min-heap via Reverse
-        let mut parent: HashMap<Coord, Coord> = HashMap::new();
+    // Whether a via may join cell and next, the same position on the layer above or below. Via
+    // blockages and the stacking rule are checked at the two cells, the spacing against the vias
+    // already placed and the ones on the path the search took to cell
+    fn via_allowed(&self, cell: Coord, next: Coord) -> bool {
+        if self.via_blockages.contains(&cell) || self.via_blockages.contains(&next) {
+            return false;
+        }
+        let parent_move = self.scratch.parent_move(self.grid.index(cell));
+        let entered_by_via = parent_move.is_some_and(|mv| MOVES[mv].0 != 0);
+        if !self.via_rules.stacking && (entered_by_via || self.vias.contains(&cell)) {
+            return false;
+        }
+
+        let cut = (cell.0.min(next.0), cell.1, cell.2);
+        self.keeps_via_spacing(cut) && self.path_keeps_via_spacing(cell, cut)
+    }
+
+    // Whether no via on the path the search took to cell joins the same two layers within the
+    // via spacing of cut
+    fn path_keeps_via_spacing(&self, cell: Coord, cut: Coord) -> bool {
+        let spacing = self.via_rules.min_spacing;
+        if spacing == 0 {
+            return true;
+        }
+        let mut current = cell;
+        while let Some(mv) = self.scratch.parent_move(self.grid.index(current)) {
+            let (dl, dr, dc) = MOVES[mv];
+            let prev = self.grid.step(current, (-dl, -dr, -dc)).unwrap();
+            if dl != 0
+                && current.0.min(prev.0) == cut.0
+                && current.1.abs_diff(cut.1) <= spacing
+                && current.2.abs_diff(cut.2) <= spacing
+            {
+                return false;
+            }
+            current = prev;
+        }
+        true
+    }
+
+    // Whether no via placed so far joins the same two layers within the via spacing of cut, a via
+    // given as its lower end
+    fn keeps_via_spacing(&self, (l, r, c): Coord) -> bool {
+        let spacing = self.via_rules.min_spacing;
+        if spacing == 0 {
+            return true;
+        }
+        let rows = r.saturating_sub(spacing)..=(r + spacing).min(self.grid.width() - 1);
+        let cols = c.saturating_sub(spacing)..=(c + spacing).min(self.grid.height() - 1);
+        !rows
+            .flat_map(|x| cols.clone().map(move |y| (x, y)))
+            .any(|(x, y)| self.has_via((l, x, y)))
+    }
+
+    // Whether a via joins layer l to layer l + 1 here, both its ends are via ends of one net
+    fn has_via(&self, (l, r, c): Coord) -> bool {
+        let (lower, upper) = ((l, r, c), (l + 1, r, c));
+        self.vias.contains(&lower)
+            && self.vias.contains(&upper)
+            && self.grid.get(lower) == self.grid.get(upper)
+    }
+
+    // Cost of the vias on a straight way from one layer to another
+    fn via_span(&self, from: usize, to: usize) -> u32 {
+        self.via_rules.costs[from.min(to)..from.max(to)]
+            .iter()
+            .sum()
+    }
+
+    // Cost of a single step within a layer, vertical means moving along the rows
+    fn step_cost(&self, l: usize, vertical: bool) -> u32 {
+        let spec = &self.layer_stack[l];
+        match spec.direction {
+            PreferredDirection::Horizontal if vertical => 1 + spec.nonpreferred_cost,
+            PreferredDirection::Vertical if !vertical => 1 + spec.nonpreferred_cost,
+            _ => 1,
+        }
+    }
+
+    // Whether a move within the layer runs off this grid but stays in the full design, which only
+    // happens on the sub-grids of parallel routing
+    fn leaves_sub_grid(&self, cell: Coord, (dl, dr, dc): (isize, isize, isize)) -> bool {
+        let (_, r, c) = cell;
+        dl == 0
+            && self.grid.step(cell, (dl, dr, dc)).is_none()
+            && (r + self.origin.0)
+                .checked_add_signed(dr)
+                .is_some_and(|r| r < self.die.0)
+            && (c + self.origin.1)
+                .checked_add_signed(dc)
+                .is_some_and(|c| c < self.die.1)
+    }
+
+    // Lower bound on the cost from one cell to another. Whatever band of layers [low, high] the path
+    // uses, it pays for the vias down to low, up to high and back to the target layer, in either
+    // order, and at least the cheapest step cost of that band for every row and column it covers
+    fn lower_bound(&self, from: Coord, to: Coord) -> u32 {
+        let rows = from.1.abs_diff(to.1) as u32;
+        let cols = from.2.abs_diff(to.2) as u32;
+        let (bottom, top) = (from.0.min(to.0), from.0.max(to.0));
+
+        let mut best = u32::MAX;
+        for low in (0..=bottom).rev() {
+            let mut vertical = u32::MAX;
+            let mut horizontal = u32::MAX;
+            for l in low..top {
+                vertical = vertical.min(self.step_cost(l, true));
+                horizontal = horizontal.min(self.step_cost(l, false));
+            }
+            for high in top..self.grid.layers() {
+                vertical = vertical.min(self.step_cost(high, true));
+                horizontal = horizontal.min(self.step_cost(high, false));
+                let vias = self.via_span(low, high)
+                    + (self.via_span(from.0, low) + self.via_span(high, to.0))
+                        .min(self.via_span(from.0, high) + self.via_span(low, to.0));
+                let bound = vias + rows * vertical + cols * horizontal;
+                best = best.min(bound);
+            }
+        }
+        best
+    }
+
+    // A* estimate of the remaining cost from a cell, zero for plain Dijkstra
+    fn estimate(&self, cell: Coord) -> u32 {
+        match self.search {
+            SearchAlgorithm::Dijkstra => 0,
+            SearchAlgorithm::AStar => self
+                .targets
+                .iter()
+                .map(|&target| self.lower_bound(cell, target))
+                .min()
+                .unwrap_or(0),
+        }
+    }
+
+    // Best known cost of a cell in the current search, with the tie-break count of its path
+    fn best_at(&self, cell: Coord) -> (u32, u32) {
+        let index = self.grid.index(cell);
+        (self.scratch.cost(index), self.scratch.tie(index))
+    }
+
+    // Tie-break count of the path through cell that continues with mv
+    fn tie_after(&self, cell: Coord, mv: usize) -> u32 {
+        let index = self.grid.index(cell);
+        let tie = self.scratch.tie(index);
+        match self.tie_break {
+            TieBreak::FewestVias if MOVES[mv].0 != 0 => tie + 1,
+            TieBreak::FewestBends => match self.scratch.parent_move(index) {
+                Some(parent) if parent != mv => tie + 1,
+                _ => tie,
+            },
+            _ => tie,
+        }
+    }
+
+    // Position of a cell in the queue among entries that are otherwise equal, highest first.
+    // Taken in the full design so that sub-grids order their cells the same way
+    fn cell_order(&self, (l, r, c): Coord) -> u64 {
+        let (width, height) = self.die;
+        let (r, c) = (r + self.origin.0, c + self.origin.1);
+        let index = ((l * width + r) * height + c) as u64;
+        match self.tie_break {
+            TieBreak::Random { seed } => {
+                // splitmix64 of the cell under the seed
+                let mut z = (seed ^ index).wrapping_add(0x9e37_79b9_7f4a_7c15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^ (z >> 31)
+            }
+            _ => index,
+        }
+    }
+
+    // Dijkstra (or A*) to find the path between start and target, returns the path cost if a target was reached
+    fn dijkstra(&mut self) -> Option<u32> {
+        // Min-heap via Reverse on the estimated total cost, the other fields break ties (see TieBreak)
+        let mut queue = BinaryHeap::new();
+        let mut escape = u32::MAX; // least a path leaving a sub-grid could cost
 
         for &source in &self.start_cords {
-            queue.push((Reverse(0), source));
+            self.scratch.start(self.grid.index(source));
+            let order = self.cell_order(source);
+            queue.push((Reverse(self.estimate(source)), Reverse(0), 0, order, source));
         }
 
-        while let Some((Reverse(cost), (l, r, c))) = queue.pop() {
-            if let Cell::Target(_) = self.grid[l][r][c] {
-                // print!("\nNet {} Cost: {}\n",self.current_net_processed, cost);
-                self.reconstruct_path((l, r, c), &parent);
-                return;
+        while let Some((_, Reverse(tie), cost, _, cell)) = queue.pop() {
+            if (cost, tie) > self.best_at(cell) {
+                continue; // a cheaper way here was found after this entry was queued
             }
-            for ((nl, nr, nc), move_cost) in self.neighbors(l, r, c) {
-                let new_cost = cost + move_cost;
+            self.expanded_cells += 1;
 
-                match self.grid[nl][nr][nc] {
-                    Cell::Free => {
-                        self.grid[nl][nr][nc] = Cell::Candidate(new_cost);
-                        parent.insert((nl, nr, nc), (l, r, c));
-                        queue.push((Reverse(new_cost), (nl, nr, nc)));
-                    }
-                    Cell::Candidate(existing_cost) => {
-                        if new_cost < existing_cost {
-                            self.grid[nl][nr][nc] = Cell::Candidate(new_cost);
-                            parent.insert((nl, nr, nc), (l, r, c));
-                            queue.push((Reverse(new_cost), (nl, nr, nc)));
-                        }
-                    }
-                    Cell::Target(existing_cost) => {
-                        if new_cost < existing_cost {
-                            self.grid[nl][nr][nc] = Cell::Target(new_cost);
-                            parent.insert((nl, nr, nc), (l, r, c));
-                            queue.push((Reverse(new_cost), (nl, nr, nc)));
-                        }
-                    }
-                    _ => {}
+            if self.targets.contains(&cell) {
+                // an equally cheap path outside might win the tie on the whole grid
+                self.current_cropped |= escape <= cost;
+                self.reconstruct_path(cell);
+                return Some(cost);
+            }
+            // a path leaving the sub-grid pays the step off it and at least the lower bound from
+            // there, both taken in the full design
+            let (l, r, c) = cell;
+            let (r, c) = (r + self.origin.0, c + self.origin.1);
+            for &(_, dr, dc) in MOVES
+                .iter()
+                .filter(|&&offset| self.leaves_sub_grid(cell, offset))
+            {
+                let beyond = (l, r.wrapping_add_signed(dr), c.wrapping_add_signed(dc));
+                let rest = self
+                    .targets
+                    .iter()
+                    .map(|&(tl, tr, tc)| {
+                        self.lower_bound(beyond, (tl, tr + self.origin.0, tc + self.origin.1))
+                    })
+                    .min();
+                let step = self.step_cost(l, dr != 0);
+                escape = escape.min(cost.saturating_add(step).saturating_add(rest.unwrap_or(0)));
+            }
+            for (mv, next, move_cost) in self.neighbors(cell) {
+                let new_cost = cost.saturating_add(move_cost);
+                let new_tie = self.tie_after(cell, mv);
+                if (new_cost, new_tie) < self.best_at(next) {
+                    self.scratch
+                        .reach(self.grid.index(next), new_cost, new_tie, mv);
+                    let estimate = new_cost.saturating_add(self.estimate(next));
+                    let order = self.cell_order(next);
+                    queue.push((Reverse(estimate), Reverse(new_tie), new_cost, order, next));
                 }
             }
         }
+        None
     }
 
-    fn reconstruct_path(&mut self, end: Coord, parent: &HashMap<Coord, Coord>) {
+    fn reconstruct_path(&mut self, end: Coord) {
         let mut current = end;
+        self.targets.retain(|&target| target != end);
+        let mut path = vec![];
+        let mut pins_reached = vec![];
 
-        while !matches!(self.grid[current.0][current.1][current.2], Cell::Start(_)) {
-            let prev = *parent.get(&current).unwrap();
+        while !matches!(self.grid.get(current), Cell::Start(_)) {
+            if self.grid.get(current) == Cell::Target {
+                pins_reached.push(current);
+            }
+            let mv = self.scratch.parent_move(self.grid.index(current)).unwrap();
+            let (dl, dr, dc) = MOVES[mv];
+            let prev = self.grid.step(current, (-dl, -dr, -dc)).unwrap();
 
             // If changing layer, mark as Via
             if current.0 != prev.0 {
                 self.vias.insert(current);
                 self.vias.insert(prev);
+                self.current_vias.push(current);
+                self.current_vias.push(prev);
+            } else {
+                self.current_wirelength += 1;
             }
 
-            self.grid[current.0][current.1][current.2] = Cell::Start(self.current_net_processed);
+            self.grid
+                .set(current, Cell::Start(self.current_net_processed));
 
-            self.start_cords.push(current);
+            path.push((prev, current));
             current = prev;
         }
+
+        // walked backwards from the target, stored from the tree outwards
+        path.reverse();
+        self.start_cords.extend(path.iter().map(|&(_, cell)| cell));
+        self.current_steps.extend(path);
+        for cell in pins_reached {
+            self.join_pin(cell);
+        }
+    }
+
+    // The rest of the shape of a pin joins the tree along with the cell of it that was reached,
+    // the shape is one piece of metal
+    fn join_pin(&mut self, reached: Coord) {
+        let Some(shape) = self
+            .current_pins
+            .iter()
+            .find(|shape| shape.contains(&reached))
+        else {
+            return;
+        };
+        let rest: Vec<Coord> = shape
+            .iter()
+            .copied()
+            .filter(|&cell| self.grid.get(cell) == Cell::Target)
+            .collect();
+        for cell in rest {
+            self.grid.set(cell, Cell::Start(self.current_net_processed));
+            self.targets.retain(|&target| target != cell);
+            self.start_cords.push(cell);
+        }
     }
 
+    // Costs are per search, only the cells the search reached are reset
     fn clear_candidates(&mut self) {
-        for layer in &mut self.grid {
-            for row in layer {
-                for cell in row {
-                    if let Cell::Candidate(_) = *cell {
-                        *cell = Cell::Free; // Clear candidates
+        self.scratch.reset();
+    }
+
+    // Searches from the current tree to the current targets, returns the path cost if one was reached
+    fn search(&mut self, net: &Net) -> Option<u32> {
+        let Some(mut margin) = self.search_margin else {
+            let found = self.dijkstra();
+            self.clear_candidates();
+            return found;
+        };
+        loop {
+            let window = self.net_window(net, margin);
+            self.window = Some(window);
+            let found = self.dijkstra();
+            self.clear_candidates();
+            let whole_grid =
+                window.rows == (0, self.width - 1) && window.cols == (0, self.height - 1);
+            if found.is_some() || whole_grid {
+                self.window = None;
+                return found;
+            }
+            margin = margin * 2 + 1;
+        }
+    }
+
+    // Bounding box of the net's pins and of the tree routed so far, grown by margin and clipped to the grid
+    fn net_window(&self, net: &Net, margin: usize) -> Window {
+        let cells = net.pin_cells().chain(&self.start_cords);
+        self.bounding_window(cells, margin)
+    }
+
+    fn bounding_window<'a>(&self, cells: impl Iterator<Item = &'a Coord>, margin: usize) -> Window {
+        let (mut rows, mut cols) = ((usize::MAX, 0), (usize::MAX, 0));
+        for &(_, r, c) in cells {
+            rows = (rows.0.min(r), rows.1.max(r));
+            cols = (cols.0.min(c), cols.1.max(c));
+        }
+        Window {
+            rows: (
+                rows.0.saturating_sub(margin),
+                (rows.1 + margin).min(self.width - 1),
+            ),
+            cols: (
+                cols.0.saturating_sub(margin),
+                (cols.1 + margin).min(self.height - 1),
+            ),
+        }
+    }
+
+    fn net_number(&self, net: &Net) -> NetId {
+        self.net_ids[&net._net_name]
+    }
+
+    // Gives every net its id, names have to be unique
+    fn assign_net_ids(&mut self, nets: &[Net]) -> Result<(), RouterError> {
+        self.net_ids.clear();
+        self.net_names.clear();
+        for net in nets {
+            let id = self.net_names.len() as NetId + 1;
+            if self.net_ids.insert(net._net_name.clone(), id).is_some() {
+                return Err(RouterError::DuplicateNet {
+                    net: net._net_name.clone(),
+                });
+            }
+            self.net_names.push(net._net_name.clone());
+        }
+        Ok(())
+    }
+
+    // Everything the router indexes with is checked before the first net is routed
+    fn validate_nets(&self, nets: &[Net]) -> Result<(), RouterError> {
+        let mut owners: HashMap<Coord, (usize, usize)> = HashMap::new(); // net and pin of every pin cell
+        for (idx, net) in nets.iter().enumerate() {
+            let name = &net._net_name;
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(RouterError::InvalidNetName { net: name.clone() });
+            }
+            if net.pins.is_empty() {
+                return Err(RouterError::EmptyNet {
+                    net: net._net_name.clone(),
+                });
+            }
+            let outside = net
+                .pin_cells()
+                .find(|&&(l, x, y)| l >= self.grid.layers() || x >= self.width || y >= self.height);
+            if let Some(&coord) = outside {
+                return Err(RouterError::PinOutOfRange {
+                    net: net._net_name.clone(),
+                    coord,
+                });
+            }
+            for (pin_idx, pin) in net.pins.iter().enumerate() {
+                for &coord in pin.cells() {
+                    match owners.insert(coord, (idx, pin_idx)) {
+                        Some(owner) if owner != (idx, pin_idx) => {
+                            return Err(RouterError::OverlappingPins {
+                                net: net._net_name.clone(),
+                                other: nets[owner.0]._net_name.clone(),
+                                coord,
+                            });
+                        }
+                        _ => {}
                     }
                 }
             }
         }
+        Ok(())
     }
 
-    pub fn process_nets(&mut self, nets: &Vec<Net>) {
+    // Pins are claimed up front so that earlier nets cannot wire over the pins of later ones
+    fn reserve_pins(&mut self, nets: &[Net]) {
         for net in nets {
-            let net_num = net
-                ._net_name
-                .strip_prefix("net")
-                .unwrap()
-                .parse::<u8>()
-                .unwrap();
-
-            self.current_net_processed = net_num;
-            // insert the start pin for this net
-            self.set_as_target(&net.pins);
-            let start_pin: &Pin = &net.pins[0]; // &net.pins[0]; TODO: to be replaced by a function that gets the closest pin to a corner
-            
-            self.original_sources.insert(start_pin.coord);
-            self.grid[start_pin.coord.0][start_pin.coord.1][start_pin.coord.2] =
-                Cell::Start(net_num);
-            //all_sources.push(start);
-            self.start_cords.clear();
-            self.start_cords.push(start_pin.coord); // Add this source to start_cords
-
-            // 3
-            for _ in 0..net.pins.len() - 1 {
-                // Perform Dijkstra to route from current sources
-                self.dijkstra();
-                self.clear_candidates(); // Reset candidate cells
-                //self.print_layers_side_by_side();
-            }
-            self.finalize_routing();
-        }
-        println!("\nFinal Layout");
-        self.print_layers_side_by_side();
-    }
-
-    fn finalize_routing(&mut self) {
-        for source in &self.start_cords {
-            match self.grid[source.0][source.1][source.2] {
-                Cell::Start(net_num) => {
-                    self.grid[source.0][source.1][source.2] = Cell::Routed(net_num)
-                } // Mark final sources as routed
-                _ => panic!("Routing on non-source") // impossible
+            let net_num = self.net_number(net);
+            for &cell in net.pin_cells() {
+                self.pins.insert(cell);
+                self.grid.set(cell, Cell::Routed(net_num));
             }
         }
     }
 
-    fn set_as_target(&mut self, pins: &Vec<Pin>) {
-        for pin in pins {
-            self.grid[pin.coord.0][pin.coord.1][pin.coord.2] = Cell::Target(MAX);
+    // Routes a single net on the current grid, returns false if some of its pins stayed unconnected
+    fn route_net(&mut self, idx: usize, net: &Net) -> Result<bool, RouterError> {
+        let source = match self.source_strategy {
+            SourceStrategy::BestOfAll => self.cheapest_source(idx, net)?,
+            _ => self.pick_source(net),
+        };
+        self.route_net_from(idx, net, source)
+    }
+
+    // Index of the pin the net is grown from
+    fn pick_source(&self, net: &Net) -> usize {
+        let pins = 0..net.pins.len();
+        match self.source_strategy {
+            SourceStrategy::FirstPin | SourceStrategy::BestOfAll => 0,
+            SourceStrategy::NearestCorner => {
+                let (width, height) = self.die;
+                let corners = [
+                    (0, 0),
+                    (0, height - 1),
+                    (width - 1, 0),
+                    (width - 1, height - 1),
+                ];
+                pins.min_by_key(|&i| {
+                    let (_, x, y) = net.pins[i].coord;
+                    let (x, y) = (x + self.origin.0, y + self.origin.1);
+                    corners
+                        .iter()
+                        .map(|&(cx, cy)| x.abs_diff(cx) + y.abs_diff(cy))
+                        .min()
+                })
+                .unwrap()
+            }
+            SourceStrategy::Centroid => {
+                // distances scaled by the pin count to stay in integers
+                let n = net.pins.len();
+                let sum_x: usize = net.pins.iter().map(|pin| pin.coord.1).sum();
+                let sum_y: usize = net.pins.iter().map(|pin| pin.coord.2).sum();
+                pins.min_by_key(|&i| {
+                    let (_, x, y) = net.pins[i].coord;
+                    (x * n).abs_diff(sum_x) + (y * n).abs_diff(sum_y)
+                })
+                .unwrap()
+            }
+            SourceStrategy::MostConstrained => pins
+                .min_by_key(|&i| self.free_neighbours(net.pins[i].coord))
+                .unwrap(),
         }
     }
 
-    pub fn initialize_obstacles(&mut self, obstacles: &Vec<Coord>) {
-        for (layer, x, y) in obstacles {
-            if *x < self.width && *y < self.height {
-                self.grid[*layer][*x as usize][*y as usize] = Cell::Blocked; // Mark as Blocked in Layer 1
+    // Neighbours off the edge of a sub-grid are counted as they are on the whole grid
+    fn free_neighbours(&self, cell: Coord) -> usize {
+        MOVES
+            .iter()
+            .filter(|&&offset| match self.grid.step(cell, offset) {
+                Some(next) => self.grid.get(next) == Cell::Free,
+                None => {
+                    let (l, r, c) = cell;
+                    let (_, dr, dc) = offset;
+                    self.leaves_sub_grid(cell, offset)
+                        && self.free_beyond.contains(&(
+                            l,
+                            (r + self.origin.0).wrapping_add_signed(dr),
+                            (c + self.origin.1).wrapping_add_signed(dc),
+                        ))
+                }
+            })
+            .count()
+    }
+
+    // Routes the net from each of its pins in turn and returns the pin that gave the cheapest tree
+    fn cheapest_source(&mut self, idx: usize, net: &Net) -> Result<usize, RouterError> {
+        let mut best: Option<(bool, u32, usize)> = None; // (failed, cost, pin)
+        for source in 0..net.pins.len() {
+            let failed = !self.route_net_from(idx, net, source)?;
+            let cost = self.routes[&idx].cost;
+            self.rip_up(idx);
+            if best
+                .is_none_or(|(best_failed, best_cost, _)| (failed, cost) < (best_failed, best_cost))
+            {
+                best = Some((failed, cost, source));
             }
         }
+        Ok(best.map_or(0, |(_, _, source)| source))
     }
 
-    pub fn print_layers_side_by_side(&self) {
-        println!("Maze Layers 1 & 2 (Side by Side)");
-        let max_rows = self.grid[0].len().max(self.grid[1].len());
-
-        for r in 0..max_rows {
-            // Layer 0
-            if r < self.grid[0].len() {
-                for c in 0..self.grid[0][r].len() {
-                    let coord = (0, r, c);
-                    let symbol = if self.vias.contains(&coord) {
-                        " V ".to_string()
-                    }else if self.original_sources.contains(&coord){
-                        " S ".to_string()
-                    }else {
-                        match self.grid[0][r][c] {
-                            Cell::Free => " . ".to_string(),
-                            Cell::Blocked => " # ".to_string(),
-                            Cell::Routed(net_num) => format!("{:^3}", net_num),
-                            Cell::Start(_) => " S ".to_string(),
-                            Cell::Target(_) => " T ".to_string(),
-                            Cell::Candidate(cost) => format!("{:^3}", cost),
+    fn route_net_from(
+        &mut self,
+        idx: usize,
+        net: &Net,
+        source: usize,
+    ) -> Result<bool, RouterError> {
+        let net_num = self.net_number(net);
+
+        self.current_net_processed = net_num;
+        self.current_vias.clear();
+        self.current_steps.clear();
+        self.current_wirelength = 0;
+        self.current_cropped = false;
+        // insert the start pin for this net, its whole shape is where the searches start
+        self.set_as_target(&net.pins);
+        let start_pin: &Pin = &net.pins[source];
+
+        self.original_sources.insert(start_pin.coord);
+        self.start_cords.clear();
+        for &cell in start_pin.cells() {
+            self.grid.set(cell, Cell::Start(net_num));
+            self.targets.retain(|&target| target != cell);
+            self.start_cords.push(cell);
+        }
+
+        let mut cost = 0;
+        let mut routed = true;
+        let mut steiner_estimate = None;
+        match self.topology {
+            NetTopology::Greedy => {
+                for _ in 0..net.pins.len() - 1 {
+                    // Perform Dijkstra to route from current sources
+                    match self.search(net) {
+                        Some(path_cost) => cost += path_cost,
+                        None => {
+                            routed = false;
+                            break;
                         }
-                    };
-                    print!("{}", symbol);
+                    }
+                }
+            }
+            NetTopology::Steiner => {
+                let (estimate, waypoints) = self.steiner_waypoints(net, start_pin.coord);
+                steiner_estimate = Some(estimate);
+                for waypoint in waypoints {
+                    // already part of the tree, or a Steiner point under obstacles
+                    self.targets = waypoint
+                        .cells
+                        .into_iter()
+                        .filter(|&cell| matches!(self.grid.get(cell), Cell::Free | Cell::Target))
+                        .collect();
+                    if self.targets.is_empty() {
+                        continue;
+                    }
+
+                    match self.search(net) {
+                        Some(path_cost) => cost += path_cost,
+                        None if waypoint.pin => {
+                            routed = false;
+                            break;
+                        }
+                        None => {}
+                    }
+                }
+            }
+        }
+        self.release_targets(&net.pins);
+        self.finalize_routing(net)?;
+
+        let pins_connected = NetRoute::count_connected(net, &self.start_cords);
+
+        self.routes.insert(
+            idx,
+            NetRoute {
+                name: net._net_name.clone(),
+                cells: self.start_cords.clone(),
+                pins: net
+                    .pins
+                    .iter()
+                    .map(|pin| pin.cells().copied().collect())
+                    .collect(),
+                steps: std::mem::take(&mut self.current_steps),
+                vias: std::mem::take(&mut self.current_vias),
+                cost,
+                wirelength: self.current_wirelength,
+                steiner_estimate,
+                source_strategy: self.source_strategy,
+                pins_connected,
+                routed,
+                cropped: self.current_cropped,
+            },
+        );
+        Ok(routed)
+    }
+
+    // Stops for a net in the order of its Steiner tree, walked from the start pin. The tree is
+    // planar, so a Steiner point may be reached on any layer
+    fn steiner_waypoints(&self, net: &Net, start: Coord) -> (u32, Vec<Waypoint>) {
+        let mut terminals = vec![(start.1, start.2)];
+        for pin in &net.pins {
+            if !terminals.contains(&(pin.coord.1, pin.coord.2)) {
+                terminals.push((pin.coord.1, pin.coord.2));
+            }
+        }
+        let tree = rectilinear_steiner_tree(&terminals);
+
+        let mut waypoints = vec![];
+        for point in std::iter::once(0).chain(tree.walk_from(0)) {
+            let (x, y) = tree.points[point];
+            if point < tree.terminals {
+                for pin in &net.pins {
+                    if (pin.coord.1, pin.coord.2) == (x, y) && pin.coord != start {
+                        waypoints.push(Waypoint {
+                            cells: pin.cells().copied().collect(),
+                            pin: true,
+                        });
+                    }
                 }
             } else {
-                print!("{}", "   ".repeat(self.grid[0][0].len()));
+                waypoints.push(Waypoint {
+                    cells: (0..self.grid.layers()).map(|l| (l, x, y)).collect(),
+                    pin: false,
+                });
             }
+        }
+        (tree.length, waypoints)
+    }
 
-            // Layer separation with vertical bar
-            print!(" │ ");
+    // Removes the wires of a net, its pins stay reserved
+    fn rip_up(&mut self, idx: usize) {
+        let Some(route) = self.routes.remove(&idx) else {
+            return;
+        };
+        for cell in &route.cells {
+            if !self.pins.contains(cell) {
+                self.grid.set(*cell, Cell::Free);
+            }
+        }
+        for via in &route.vias {
+            self.vias.remove(via);
+        }
+        if let Some(start) = route.cells.first() {
+            self.original_sources.remove(start);
+        }
+    }
 
-            // Layer 1
-            if r < self.grid[1].len() {
-                for c in 0..self.grid[1][r].len() {
-                    let coord = (1, r, c);
-                    let symbol = if self.vias.contains(&coord) {
-                        " V ".to_string()
-                    }else if self.original_sources.contains(&coord){
-                        " S ".to_string()
-                    }else {
-                        match self.grid[1][r][c] {
-                            Cell::Free => " . ".to_string(),
-                            Cell::Blocked => " # ".to_string(),
-                            Cell::Routed(net_num) => format!("{:^3}", net_num),
-                            Cell::Start(_) => " S ".to_string(),
-                            Cell::Target(_) => " T ".to_string(),
-                            Cell::Candidate(cost) => format!("{:^3}", cost),
-                        }
-                    };
-                    print!("{}", symbol);
+    // Routes the net where the wires of other nets may be crossed at a high cost, the nets whose
+    // wires that route crosses are the ones blocking it. The probe runs on the live grid with the
+    // other wires lifted, and is ripped up again before they are put back
+    fn find_blocking_nets(&mut self, idx: usize, nets: &[Net]) -> Result<Vec<usize>, RouterError> {
+        let mut lifted: Vec<(Coord, Cell)> = vec![];
+        let mut lifted_vias: Vec<Coord> = vec![];
+        for route in self.routes.values() {
+            for &cell in &route.cells {
+                if !self.pins.contains(&cell) {
+                    lifted.push((cell, self.grid.get(cell)));
+                }
+            }
+            lifted_vias.extend(&route.vias);
+        }
+        for &(cell, _) in &lifted {
+            self.grid.set(cell, Cell::Free);
+            self.penalized.insert(cell);
+        }
+        for via in &lifted_vias {
+            self.vias.remove(via);
+        }
+
+        // the probe's searches are not part of the routing effort
+        let expanded_cells = self.expanded_cells;
+        let routed = self.route_net(idx, &nets[idx]);
+        self.expanded_cells = expanded_cells;
+        let probe = self.routes.get(&idx).cloned();
+        self.rip_up(idx);
+        for (cell, state) in lifted {
+            self.grid.set(cell, state);
+        }
+        self.vias.extend(lifted_vias);
+        self.penalized.clear();
+
+        let (true, Some(probe)) = (routed?, probe) else {
+            return Ok(vec![]); // blocked by obstacles or pins, ripping up wires will not help
+        };
+
+        // Wires and vias too close to the route block it as much as the wires it crosses
+        let mut path: HashSet<Coord> = HashSet::new();
+        for &cell in &probe.cells {
+            path.insert(cell);
+            if !self.pins.contains(&cell) {
+                path.extend(self.spacing_zone(cell));
+            }
+        }
+        let spacing = self.via_rules.min_spacing;
+        let cuts: Vec<Coord> = probe.via_cuts().collect();
+        let near_cut = |(l, r, c): Coord| {
+            cuts.iter().any(|cut| {
+                cut.0 == l && cut.1.abs_diff(r) <= spacing && cut.2.abs_diff(c) <= spacing
+            })
+        };
+        let mut blockers: Vec<usize> = self
+            .routes
+            .iter()
+            .filter(|(&other, route)| {
+                other != idx
+                    && (route.cells.iter().any(|c| path.contains(c))
+                        || (spacing > 0 && route.via_cuts().any(near_cut)))
+            })
+            .map(|(&other, _)| other)
+            .collect();
+        blockers.sort();
+        Ok(blockers)
+    }
+
+    pub fn process_nets(&mut self, nets: &[Net]) -> Result<RoutingResult, RouterError> {
+        self.validate_nets(nets)?;
+        self.assign_net_ids(nets)?;
+        self.reserve_pins(nets);
+        let order = self.net_order.clone();
+        self.route_in_order(nets, &order)?;
+        Ok(self.routing_result())
+    }
+
+    // Lays routes read back from a solution on the grid instead of searching for them. Each path
+    // is a net's name and the cells of one wire, the first path of a net starts on one of its pins
+    // and every later one on a cell the net already has. Wires have to keep to the same spacing,
+    // stacking and blockage rules as a search. Nets without a path stay unrouted
+    pub fn load_routes(
+        &mut self,
+        nets: &[Net],
+        paths: &[(String, Vec<Coord>)],
+    ) -> Result<RoutingResult, RouterError> {
+        self.validate_nets(nets)?;
+        self.assign_net_ids(nets)?;
+        self.reserve_pins(nets);
+        if let Some((name, _)) = paths
+            .iter()
+            .find(|(name, _)| !self.net_ids.contains_key(name))
+        {
+            return Err(RouterError::UnknownName {
+                kind: "net",
+                name: name.clone(),
+            });
+        }
+        for (idx, net) in nets.iter().enumerate() {
+            let wires: Vec<&[Coord]> = paths
+                .iter()
+                .filter(|(name, _)| *name == net._net_name)
+                .map(|(_, cells)| cells.as_slice())
+                .collect();
+            self.load_net(idx, net, &wires)?;
+        }
+        Ok(self.routing_result())
+    }
+
+    // Claims the cells of a net's wires in the order given, the same bookkeeping a search leaves
+    fn load_net(&mut self, idx: usize, net: &Net, wires: &[&[Coord]]) -> Result<(), RouterError> {
+        let net_num = self.net_number(net);
+        let invalid = |coord, message| RouterError::InvalidRoute {
+            net: net._net_name.clone(),
+            coord,
+            message,
+        };
+        self.current_net_processed = net_num;
+        self.current_vias.clear();
+        self.current_steps.clear();
+        self.current_wirelength = 0;
+        self.start_cords.clear();
+        let mut cost = 0;
+
+        for wire in wires {
+            let Some(&first) = wire.first() else {
+                continue;
+            };
+            let on_net = if self.start_cords.is_empty() {
+                net.pin_cells().any(|&cell| cell == first)
+            } else {
+                self.start_cords.contains(&first)
+            };
+            if !on_net {
+                return Err(invalid(first, "the wire does not start on the net"));
+            }
+            self.load_cell(net, first);
+
+            let mut entered_by_via = false;
+            for pair in wire.windows(2) {
+                let (prev, cell) = (pair[0], pair[1]);
+                if !self.grid.contains(cell) {
+                    return Err(invalid(cell, "outside the grid"));
+                }
+                let mv = MOVES
+                    .iter()
+                    .position(|&offset| self.grid.step(prev, offset) == Some(cell))
+                    .ok_or(invalid(cell, "not next to the cell before it"))?;
+                match self.grid.get(cell) {
+                    Cell::Free => {}
+                    Cell::Routed(owner) if owner == net_num => {}
+                    _ => return Err(invalid(cell, "blocked or used by another net")),
+                }
+                // the same rules a search keeps to, pins of the net may sit next to anything
+                let own_pin = net.pin_cells().any(|&pin| pin == cell);
+                if !own_pin && !self.keeps_spacing(cell, net_num) {
+                    return Err(invalid(
+                        cell,
+                        "closer to another net than the layer spacing",
+                    ));
+                }
+                if MOVES[mv].0 != 0 {
+                    if self.via_blockages.contains(&prev) || self.via_blockages.contains(&cell) {
+                        return Err(invalid(cell, "a via on a via blockage"));
+                    }
+                    if !self.via_rules.stacking && (entered_by_via || self.vias.contains(&prev)) {
+                        return Err(invalid(cell, "a via stacked on another"));
+                    }
+                    if !self.keeps_via_spacing((cell.0.min(prev.0), cell.1, cell.2)) {
+                        return Err(invalid(
+                            cell,
+                            "a via closer to another than the via spacing",
+                        ));
+                    }
+                    self.vias.insert(cell);
+                    self.vias.insert(prev);
+                    self.current_vias.push(cell);
+                    self.current_vias.push(prev);
+                    cost += self.via_rules.costs[cell.0.min(prev.0)];
+                } else {
+                    self.current_wirelength += 1;
+                    cost += self.step_cost(prev.0, MOVES[mv].1 != 0);
                 }
+                entered_by_via = MOVES[mv].0 != 0;
+                self.current_steps.push((prev, cell));
+                self.load_cell(net, cell);
             }
+        }
 
-            println!(" │ Row {}", r);
+        // a net with no wire is left as its first pin, like a net no search could route
+        if self.start_cords.is_empty() {
+            self.start_cords.extend(net.pins[0].cells());
         }
+        let source = net
+            .pins
+            .iter()
+            .find(|pin| pin.cells().any(|&cell| cell == self.start_cords[0]))
+            .map_or(self.start_cords[0], |pin| pin.coord);
+        self.original_sources.insert(source);
+
+        // a wire that reaches only some of the pins leaves the net partly routed, as a search would
+        let pins_connected = NetRoute::count_connected(net, &self.start_cords);
+        self.routes.insert(
+            idx,
+            NetRoute {
+                name: net._net_name.clone(),
+                cells: std::mem::take(&mut self.start_cords),
+                pins: net
+                    .pins
+                    .iter()
+                    .map(|pin| pin.cells().copied().collect())
+                    .collect(),
+                steps: std::mem::take(&mut self.current_steps),
+                vias: std::mem::take(&mut self.current_vias),
+                cost,
+                wirelength: self.current_wirelength,
+                steiner_estimate: None,
+                source_strategy: self.source_strategy,
+                pins_connected,
+                routed: pins_connected == net.pins.len(),
+                cropped: false,
+            },
+        );
+        Ok(())
+    }
+
+    // A cell joins the net being loaded, and with a pin cell the rest of the pin's shape
+    fn load_cell(&mut self, net: &Net, cell: Coord) {
+        let shape = net
+            .pins
+            .iter()
+            .find(|pin| pin.cells().any(|&known| known == cell));
+        let cells: Vec<Coord> = match shape {
+            // the first pin goes in whole and in its own order, as the start pin of a search does
+            Some(pin) if self.start_cords.is_empty() => pin.cells().copied().collect(),
+            Some(pin) => std::iter::once(cell).chain(pin.cells().copied()).collect(),
+            None => vec![cell],
+        };
+        for cell in cells {
+            if !self.start_cords.contains(&cell) {
+                self.grid
+                    .set(cell, Cell::Routed(self.current_net_processed));
+                self.start_cords.push(cell);
+            }
+        }
+    }
+
+    // Snapshot of what every net looks like on the grid now
+    pub fn routing_result(&self) -> RoutingResult {
+        let mut indices: Vec<&usize> = self.routes.keys().collect();
+        indices.sort();
+        let nets = indices
+            .into_iter()
+            .map(|idx| {
+                let route = &self.routes[idx];
+                let (segments, vias) = NetResult::from_steps(&route.steps);
+                let status = route.status();
+                NetResult {
+                    name: route.name.clone(),
+                    status,
+                    cells: route.cells.clone(),
+                    pins: route.pins.clone(),
+                    paths: NetResult::paths_from_steps(&route.steps),
+                    segments,
+                    vias,
+                    cost: route.cost,
+                    wirelength: route.wirelength,
+                    steiner_estimate: route.steiner_estimate,
+                    source_strategy: route.source_strategy,
+                }
+            })
+            .collect();
+        RoutingResult {
+            nets,
+            expanded_cells: self.expanded_cells,
+            violations: self.spacing_violations(),
+        }
+    }
+
+    // Pairs of cells of different nets closer than their layer's minimum spacing, where at least
+    // one of the two is wire rather than pin. The search keeps to the spacing, so these only show
+    // up on grids that were routed before the rules were set. Each pair is reported once
+    pub fn spacing_violations(&self) -> Vec<SpacingViolation> {
+        let mut violations = vec![];
+        for (cell, state) in self.grid.iter() {
+            let Cell::Routed(net) = state else {
+                continue;
+            };
+            let index = self.grid.index(cell);
+            for near in self.spacing_zone(cell) {
+                let Cell::Routed(other) = self.grid.get(near) else {
+                    continue;
+                };
+                if other == net
+                    || self.grid.index(near) < index
+                    || (self.pins.contains(&cell) && self.pins.contains(&near))
+                {
+                    continue;
+                }
+                violations.push(SpacingViolation {
+                    layer: cell.0,
+                    nets: (
+                        self.net_names[net as usize - 1].clone(),
+                        self.net_names[other as usize - 1].clone(),
+                    ),
+                    cells: (cell, near),
+                });
+            }
+        }
+        violations
+    }
+
+    // Sum of the path costs of the nets as they are routed now
+    pub fn total_cost(&self) -> u32 {
+        self.routes.values().map(|route| route.cost).sum()
+    }
+
+    // Cells taken off the search queues so far, over every search including rip-up probes and reroutes
+    pub fn expanded_cells(&self) -> usize {
+        self.expanded_cells
+    }
+
+    fn route_in_order(&mut self, nets: &[Net], order: &NetOrder) -> Result<(), RouterError> {
+        if let NetOrder::BestOf(orders) = order {
+            // every order is routed on the live grid and ripped up again, only the routes of the
+            // best one are kept aside to be put back
+            let before = self.routed_state();
+            let mut best: Option<((usize, u32), RoutedState)> = None;
+            for order in orders {
+                self.route_in_order(nets, order)?;
+                let score = (self.failed_nets().len(), self.total_cost());
+                if best
+                    .as_ref()
+                    .is_none_or(|(best_score, _)| score < *best_score)
+                {
+                    best = Some((score, self.routed_state()));
+                }
+                self.restore_routed_state(before.clone());
+            }
+            if let Some((_, best)) = best {
+                self.restore_routed_state(best);
+            }
+            return Ok(());
+        }
+
+        let sequence = Self::net_sequence(nets, order);
+        match self.mode {
+            RoutingMode::Sequential => self.route_sequential(nets, sequence.into()).map(|_| ()),
+            RoutingMode::Negotiated { max_iterations } => {
+                self.route_negotiated(nets, &sequence, max_iterations)
+            }
+            RoutingMode::Parallel { threads, margin } => {
+                self.route_parallel(nets, &sequence, threads, margin)
+            }
+        }
+    }
+
+    fn routed_state(&self) -> RoutedState {
+        RoutedState {
+            routes: self.routes.clone(),
+            vias: self.vias.clone(),
+            original_sources: self.original_sources.clone(),
+            expanded_cells: self.expanded_cells,
+        }
+    }
+
+    // Rips up every net routed now and lays the routes of the state in their place
+    fn restore_routed_state(&mut self, state: RoutedState) {
+        let routed: Vec<usize> = self.routes.keys().copied().collect();
+        for idx in routed {
+            self.rip_up(idx);
+        }
+        for route in state.routes.values() {
+            let net_num = self.net_ids[&route.name];
+            for &cell in &route.cells {
+                self.grid.set(cell, Cell::Routed(net_num));
+            }
+        }
+        self.routes = state.routes;
+        self.vias = state.vias;
+        self.original_sources = state.original_sources;
+        self.expanded_cells = state.expanded_cells;
+    }
+
+    // Indices of the nets in the order they get routed, ties keep the input order
+    fn net_sequence(nets: &[Net], order: &NetOrder) -> Vec<usize> {
+        let mut sequence: Vec<usize> = (0..nets.len()).collect();
+        let half_perimeter = |net: &Net| {
+            let xs = net.pins.iter().map(|pin| pin.coord.1);
+            let ys = net.pins.iter().map(|pin| pin.coord.2);
+            (xs.clone().max().unwrap_or(0) - xs.min().unwrap_or(0))
+                + (ys.clone().max().unwrap_or(0) - ys.min().unwrap_or(0))
+        };
+        let steiner_length = |net: &Net| {
+            let points: Vec<(usize, usize)> = net
+                .pins
+                .iter()
+                .map(|pin| (pin.coord.1, pin.coord.2))
+                .collect();
+            rectilinear_steiner_tree(&points).length
+        };
+        match order {
+            NetOrder::FileOrder | NetOrder::BestOf(_) => {}
+            NetOrder::HalfPerimeter => sequence.sort_by_key(|&i| half_perimeter(&nets[i])),
+            NetOrder::PinCount => sequence.sort_by_key(|&i| nets[i].pins.len()),
+            NetOrder::Priority => sequence.sort_by_key(|&i| Reverse(nets[i].priority)),
+            // one tree per net, not one per comparison
+            NetOrder::ShortestFirst => sequence.sort_by_cached_key(|&i| steiner_length(&nets[i])),
+            NetOrder::LongestFirst => {
+                sequence.sort_by_cached_key(|&i| Reverse(steiner_length(&nets[i])))
+            }
+        }
+        sequence
+    }
+
+    // Nets are routed in the given order, a net that fails rips up the nets in its way
+    // and is routed again before them. Returns how many times nets were ripped up
+    fn route_sequential(
+        &mut self,
+        nets: &[Net],
+        mut queue: VecDeque<usize>,
+    ) -> Result<usize, RouterError> {
+        let mut ripups = 0;
+        while let Some(idx) = queue.pop_front() {
+            if self.route_net(idx, &nets[idx])? || ripups >= self.max_ripup_iterations {
+                continue;
+            }
+
+            self.rip_up(idx);
+            let blockers = self.find_blocking_nets(idx, nets)?;
+            if blockers.is_empty() {
+                self.route_net(idx, &nets[idx])?; // keep whatever part of it can be routed
+                continue;
+            }
+
+            ripups += 1;
+            for &blocker in &blockers {
+                self.rip_up(blocker);
+            }
+            queue.retain(|n| !blockers.contains(n));
+            for &blocker in blockers.iter().rev() {
+                queue.push_front(blocker);
+            }
+            queue.push_front(idx);
+        }
+        Ok(ripups)
+    }
+
+    // PathFinder: every net is rerouted each iteration while cells used by several nets
+    // get more expensive, until no cell is shared anymore
+    fn route_negotiated(
+        &mut self,
+        nets: &[Net],
+        sequence: &[usize],
+        max_iterations: usize,
+    ) -> Result<(), RouterError> {
+        self.congestion = Some(Congestion {
+            present_factor: 1,
+            ..Default::default()
+        });
+
+        for _ in 0..max_iterations {
+            for &idx in sequence {
+                self.release(idx);
+                self.route_net(idx, &nets[idx])?;
+                self.lift(idx);
+            }
+
+            let congestion = self.congestion.as_mut().unwrap();
+            let overused = congestion.overused_cells();
+            if overused.is_empty() {
+                break;
+            }
+            for (cell, occupancy) in overused {
+                let history = congestion.history.entry(cell).or_insert(0);
+                *history = history.saturating_add(HISTORY_INCREMENT * (occupancy - 1));
+            }
+            congestion.present_factor =
+                (congestion.present_factor * PRESENT_FACTOR_GROWTH).min(MAX_PRESENT_FACTOR);
+        }
+        self.congestion = None;
+
+        // Nets keep their negotiated route unless an earlier net already claimed one of its cells
+        // or runs, or puts a via, closer to it than the spacing allows. Those left over are routed
+        // again one after another
+        let mut claimed: HashSet<Coord> = HashSet::new();
+        let mut leftover = VecDeque::new();
+        for &idx in sequence {
+            let route = &self.routes[&idx];
+            let net_num = self.net_number(&nets[idx]);
+            if route.cells.iter().any(|cell| {
+                claimed.contains(cell)
+                    || (!self.pins.contains(cell) && !self.keeps_spacing(*cell, net_num))
+            }) || route.via_cuts().any(|cut| !self.keeps_via_spacing(cut))
+            {
+                self.release(idx);
+                leftover.push_back(idx);
+            } else {
+                claimed.extend(route.cells.iter().filter(|cell| !self.pins.contains(cell)));
+                self.commit(idx, &nets[idx]);
+            }
+        }
+        self.route_sequential(nets, leftover).map(|_| ())
+    }
+
+    // Nets whose regions overlap are grouped, the groups are routed on sub-grids of their own on
+    // separate threads and put back on the grid. A region is the bounding box of a net's pins
+    // grown by margin, so groups never touch each other's cells and the result does not depend
+    // on the number of threads. The result is only kept when routing on the whole grid could not
+    // have done anything else: no group ripped up a net, every net was routed, and no search
+    // reached the edge of its region while a way around outside was as cheap. Otherwise every
+    // net is ripped up and the design is routed sequentially
+    fn route_parallel(
+        &mut self,
+        nets: &[Net],
+        sequence: &[usize],
+        threads: usize,
+        margin: usize,
+    ) -> Result<(), RouterError> {
+        let groups = self.independent_groups(nets, sequence, margin);
+        let threads = match threads {
+            0 => thread::available_parallelism().map_or(1, |cores| cores.get()),
+            n => n,
+        }
+        .clamp(1, groups.len().max(1));
+
+        let next = AtomicUsize::new(0);
+        let mut routed: Vec<(usize, RoutedGroup)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = vec![];
+                        loop {
+                            let group = next.fetch_add(1, Ordering::Relaxed);
+                            let Some((window, members)) = groups.get(group) else {
+                                break done;
+                            };
+                            let (mut sub, sub_nets) = self.sub_maze(*window, members, nets);
+                            let result = sub
+                                .route_sequential(&sub_nets, (0..members.len()).collect())
+                                .map(|ripups| (sub, ripups));
+                            done.push((group, result));
+                        }
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        routed.sort_by_key(|(group, _)| *group);
+
+        let mut ripups = 0;
+        for ((window, members), (_, sub)) in groups.iter().zip(routed) {
+            let (sub, sub_ripups) = sub?;
+            ripups += sub_ripups;
+            self.merge_sub_maze(*window, members, sub);
+        }
+
+        let diverged = sequence
+            .iter()
+            .any(|idx| !self.routes[idx].routed || self.routes[idx].cropped);
+        if ripups == 0 && !diverged {
+            return Ok(());
+        }
+        for &idx in sequence {
+            self.rip_up(idx);
+        }
+        self.route_sequential(nets, sequence.iter().copied().collect())
+            .map(|_| ())
+    }
+
+    // Nets whose regions overlap, directly or through other nets, with the union of their regions.
+    // Regions closer than the widest wire or via spacing count as overlapping, as wires and vias
+    // on either side could not see each other, and so do regions side by side, whose edge cells
+    // neighbour each other. Members keep their routing order
+    fn independent_groups(
+        &self,
+        nets: &[Net],
+        sequence: &[usize],
+        margin: usize,
+    ) -> Vec<(Window, Vec<usize>)> {
+        let mut groups: Vec<(Window, Vec<usize>)> = sequence
+            .iter()
+            .map(|&idx| {
+                (
+                    self.bounding_window(nets[idx].pin_cells(), margin),
+                    vec![idx],
+                )
+            })
+            .collect();
+        let gap = self
+            .layer_stack
+            .iter()
+            .map(|spec| spec.min_spacing)
+            .chain([self.via_rules.min_spacing])
+            .max()
+            .unwrap_or(0)
+            + 1;
+
+        let mut merged = true;
+        while merged {
+            merged = false;
+            let mut i = 0;
+            while i < groups.len() {
+                let mut j = i + 1;
+                while j < groups.len() {
+                    if groups[i].0.grown(gap).overlaps(&groups[j].0) {
+                        let (window, members) = groups.remove(j);
+                        groups[i].0 = groups[i].0.union(&window);
+                        groups[i].1.extend(members);
+                        merged = true;
+                    } else {
+                        j += 1;
+                    }
+                }
+                i += 1;
+            }
+        }
+
+        let rank: HashMap<usize, usize> = sequence
+            .iter()
+            .enumerate()
+            .map(|(rank, &idx)| (idx, rank))
+            .collect();
+        for (_, members) in &mut groups {
+            members.sort_by_key(|idx| rank[idx]);
+        }
+        groups
+    }
+
+    // A maze covering only the window, with the same cells and settings, and the member nets
+    // moved onto it
+    fn sub_maze(&self, window: Window, members: &[usize], nets: &[Net]) -> (Maze, Vec<Net>) {
+        let (r0, c0) = (window.rows.0, window.cols.0);
+        let width = window.rows.1 - r0 + 1;
+        let height = window.cols.1 - c0 + 1;
+        let layers = self.grid.layers();
+
+        let mut sub = Maze::new(width, height, layers, 0, 0);
+        for l in 0..layers {
+            for r in 0..width {
+                for c in 0..height {
+                    sub.grid.set((l, r, c), self.grid.get((l, r + r0, c + c0)));
+                }
+            }
+        }
+        sub.layer_stack = self.layer_stack.clone();
+        sub.via_rules = self.via_rules.clone();
+        sub.via_blockages = self
+            .via_blockages
+            .iter()
+            .filter(|&&cell| window.contains(cell))
+            .map(|&(l, r, c)| (l, r - r0, c - c0))
+            .collect();
+        sub.pins = self
+            .pins
+            .iter()
+            .filter(|&&pin| window.contains(pin))
+            .map(|&(l, r, c)| (l, r - r0, c - c0))
+            .collect();
+        sub.max_ripup_iterations = self.max_ripup_iterations;
+        sub.search = self.search;
+        sub.topology = self.topology;
+        sub.source_strategy = self.source_strategy;
+        sub.search_margin = self.search_margin;
+        sub.tie_break = self.tie_break;
+        sub.origin = (self.origin.0 + r0, self.origin.1 + c0);
+        sub.die = self.die;
+        let (r1, c1) = (window.rows.1, window.cols.1);
+        let beyond = (c0..=c1)
+            .flat_map(|c| [(r0.wrapping_sub(1), c), (r1 + 1, c)])
+            .chain((r0..=r1).flat_map(|r| [(r, c0.wrapping_sub(1)), (r, c1 + 1)]));
+        for (r, c) in beyond.filter(|&(r, c)| r < self.width && c < self.height) {
+            for l in 0..layers {
+                if self.grid.get((l, r, c)) == Cell::Free {
+                    sub.free_beyond.insert((l, r, c));
+                }
+            }
+        }
+
+        let sub_nets = members
+            .iter()
+            .map(|&idx| {
+                let net = &nets[idx];
+                sub.net_ids
+                    .insert(net._net_name.clone(), self.net_ids[&net._net_name]);
+                Net {
+                    _net_name: net._net_name.clone(),
+                    pins: net
+                        .pins
+                        .iter()
+                        .map(|pin| {
+                            let shift = |&(l, r, c): &Coord| (l, r - r0, c - c0);
+                            Pin {
+                                coord: shift(&pin.coord),
+                                shape: pin.shape.iter().map(shift).collect(),
+                            }
+                        })
+                        .collect(),
+                    priority: net.priority,
+                }
+            })
+            .collect();
+        (sub, sub_nets)
+    }
+
+    // Puts the cells and routes of a routed sub-maze back where its window is
+    fn merge_sub_maze(&mut self, window: Window, members: &[usize], sub: Maze) {
+        let origin = (window.rows.0, window.cols.0);
+        let shift = |(l, r, c): Coord| (l, r + origin.0, c + origin.1);
+        for (cell, state) in sub.grid.iter() {
+            self.grid.set(shift(cell), state);
+        }
+        for (local, route) in sub.routes {
+            self.routes.insert(members[local], route.shifted(origin));
+        }
+        self.vias.extend(sub.vias.into_iter().map(shift));
+        self.original_sources
+            .extend(sub.original_sources.into_iter().map(shift));
+        self.expanded_cells += sub.expanded_cells;
+    }
+
+    // Takes the wires of a freshly routed net off the grid so other nets may share its cells
+    fn lift(&mut self, idx: usize) {
+        let route = &self.routes[&idx];
+        let congestion = self.congestion.as_mut().unwrap();
+        for cell in &route.cells {
+            if !self.pins.contains(cell) {
+                self.grid.set(*cell, Cell::Free);
+                *congestion.occupancy.entry(*cell).or_insert(0) += 1;
+            }
+        }
+        for via in &route.vias {
+            self.vias.remove(via);
+        }
+    }
+
+    // Forgets the lifted route of a net
+    fn release(&mut self, idx: usize) {
+        let Some(route) = self.routes.remove(&idx) else {
+            return;
+        };
+        if let Some(congestion) = self.congestion.as_mut() {
+            for cell in &route.cells {
+                if let Some(occupancy) = congestion.occupancy.get_mut(cell) {
+                    *occupancy -= 1;
+                }
+            }
+        }
+        if let Some(start) = route.cells.first() {
+            self.original_sources.remove(start);
+        }
+    }
+
+    // Puts a lifted route back on the grid
+    fn commit(&mut self, idx: usize, net: &Net) {
+        let net_num = self.net_number(net);
+        let route = &self.routes[&idx];
+        for cell in &route.cells {
+            self.grid.set(*cell, Cell::Routed(net_num));
+        }
+        self.vias.extend(route.vias.iter().copied());
+    }
+
+    // Names of the nets that still have unconnected pins, in input order
+    pub fn failed_nets(&self) -> Vec<&str> {
+        let mut failed: Vec<(usize, &str)> = self
+            .routes
+            .iter()
+            .filter(|(_, route)| !route.routed)
+            .map(|(&idx, route)| (idx, route.name.as_str()))
+            .collect();
+        failed.sort();
+        failed.into_iter().map(|(_, name)| name).collect()
+    }
+
+    // Pins that were never reached go back to being reserved for their net
+    fn release_targets(&mut self, pins: &[Pin]) {
+        for &cell in pins.iter().flat_map(|pin| pin.cells()) {
+            if self.grid.get(cell) == Cell::Target {
+                self.grid
+                    .set(cell, Cell::Routed(self.current_net_processed));
+            }
+        }
+    }
+
+    fn finalize_routing(&mut self, net: &Net) -> Result<(), RouterError> {
+        for source in &self.start_cords {
+            match self.grid.get(*source) {
+                Cell::Start(net_num) => self.grid.set(*source, Cell::Routed(net_num)), // Mark final sources as routed
+                _ => {
+                    return Err(RouterError::UnexpectedCell {
+                        net: net._net_name.clone(),
+                        coord: *source,
+                    })
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn set_as_target(&mut self, pins: &[Pin]) {
+        self.current_pins = pins
+            .iter()
+            .map(|pin| pin.cells().copied().collect())
+            .collect();
+        self.targets = self.current_pins.concat();
+        for &target in &self.targets {
+            self.grid.set(target, Cell::Target);
+        }
+    }
+
+    // Cells of a blockage that lie on the grid, on each of its layers
+    fn blockage_cells(&self, blockage: &Blockage) -> impl Iterator<Item = Coord> {
+        let layers = match blockage.layer {
+            Some(l) => l..(l + 1).min(self.grid.layers()),
+            None => 0..self.grid.layers(),
+        };
+        let rows = blockage.rows.0..=blockage.rows.1.min(self.grid.width() - 1);
+        let cols = blockage.cols.0..=blockage.cols.1.min(self.grid.height() - 1);
+        layers.flat_map(move |l| {
+            let cols = cols.clone();
+            rows.clone()
+                .flat_map(move |x| cols.clone().map(move |y| (l, x, y)))
+        })
+    }
+
+    // Blocks every cell of the rectangles, a row at a time. The rectangles are remembered so a
+    // cell can be traced back to the named region covering it
+    pub fn initialize_blockages(&mut self, blockages: &[Blockage]) {
+        for blockage in blockages {
+            let layers = match blockage.layer {
+                Some(l) => l..l + 1,
+                None => 0..self.grid.layers(),
+            };
+            for l in layers {
+                self.grid
+                    .fill(l, blockage.rows, blockage.cols, Cell::Blocked);
+            }
+            self.blockages.push(blockage.clone());
+        }
+    }
+
+    // Name of the first BLOCKAGE region that covers the cell
+    pub fn blockage_at(&self, coord: Coord) -> Option<&str> {
+        self.blockages
+            .iter()
+            .filter(|blockage| blockage.contains(coord))
+            .find_map(|blockage| blockage.name.as_deref())
+    }
+
+    // Cells that wires may still run through but no via may start or end at
+    pub fn initialize_via_blockages(&mut self, blockages: &[Blockage]) {
+        let cells: Vec<Coord> = blockages
+            .iter()
+            .flat_map(|blockage| self.blockage_cells(blockage))
+            .collect();
+        self.via_blockages.extend(cells);
+    }
+
+    pub fn initialize_obstacles(&mut self, obstacles: &Vec<Coord>) {
+        for &obstacle in obstacles {
+            if self.grid.contains(obstacle) {
+                self.grid.set(obstacle, Cell::Blocked); // Mark as Blocked
+                self.blockages.push(Blockage::cell(obstacle));
+            }
+        }
+    }
+
+    pub fn print_layers_side_by_side(&self) {
+        print!("{}", self.layers_side_by_side());
+    }
+
+    // Routed cells show the net's name when every name fits a cell, its id and a legend otherwise
+    fn layers_side_by_side(&self) -> String {
+        let mut out = String::new();
+        let layer_names: Vec<String> = (1..=self.grid.layers()).map(|l| l.to_string()).collect();
+        out += &format!("Maze Layers {} (Side by Side)\n", layer_names.join(" & "));
+        let longest_name = self.net_names.iter().map(|name| name.chars().count()).max();
+        let by_name = longest_name.is_some_and(|len| len <= MAX_CELL_NAME);
+        // cells are wide enough for the longest label with a space on each side
+        let width = match longest_name {
+            Some(len) if by_name => len,
+            _ => self.net_names.len().to_string().len(),
+        }
+        .max(1)
+            + 2;
+
+        for r in 0..self.width {
+            for l in 0..self.grid.layers() {
+                // Layer separation with vertical bar
+                if l > 0 {
+                    out += " │ ";
+                }
+                for c in 0..self.height {
+                    let coord = (l, r, c);
+                    let symbol = if self.vias.contains(&coord) {
+                        "V".to_string()
+                    } else if self.original_sources.contains(&coord) {
+                        "S".to_string()
+                    } else {
+                        match self.grid.get(coord) {
+                            Cell::Free => ".".to_string(),
+                            Cell::Blocked => "#".to_string(),
+                            Cell::Routed(net_num) if by_name => {
+                                self.net_names[net_num as usize - 1].clone()
+                            }
+                            Cell::Routed(net_num) => net_num.to_string(),
+                            Cell::Start(_) => "S".to_string(),
+                            Cell::Target => "T".to_string(),
+                        }
+                    };
+                    out += &format!("{:^width$}", symbol);
+                }
+            }
+
+            out += &format!(" │ Row {}\n", r);
+        }
+
+        if !by_name {
+            for (id, name) in self.net_names.iter().enumerate() {
+                out += &format!("{:>width$} = {}\n", id + 1, name);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::lee_maze::lee_maze::{
+        Cell, Maze, NetOrder, NetTopology, RoutingMode, SearchAlgorithm, SourceStrategy, TieBreak,
+    };
+    use crate::{
+        Blockage, Coord, LayerSpec, Net, NetStatus, Pin, PreferredDirection, RouterError,
+        RoutingResult, SpacingViolation, Via, ViaRules,
+    };
+
+    fn net(name: &str, coords: &[(usize, usize, usize)]) -> Net {
+        Net {
+            _net_name: name.to_string(),
+            pins: coords.iter().map(|&coord| Pin::at(coord)).collect(),
+            priority: 0,
+        }
+    }
+
+    // Single usable layer, the second one is fully blocked
+    fn single_layer_maze(width: usize, height: usize, obstacles: &[(usize, usize)]) -> Maze {
+        let mut maze = Maze::new(width, height, 2, 10, 0);
+        let mut blocked: Vec<(usize, usize, usize)> =
+            obstacles.iter().map(|&(x, y)| (0, x, y)).collect();
+        for x in 0..width {
+            for y in 0..height {
+                blocked.push((1, x, y));
+            }
+        }
+        maze.initialize_obstacles(&blocked);
+        maze
+    }
+
+    #[test]
+    fn test_ripup_reroutes_blocking_net() {
+        // net1 takes the short corridor on row 2 which is the only way net2 can go,
+        // it has to be ripped up and sent around through row 0
+        let mut maze = single_layer_maze(4, 5, &[(1, 1), (1, 2), (1, 3), (3, 0), (3, 2), (3, 4)]);
+        let nets = vec![
+            net("net1", &[(0, 2, 0), (0, 2, 4)]),
+            net("net2", &[(0, 3, 1), (0, 3, 3)]),
+        ];
+        maze.process_nets(&nets).unwrap();
+
+        assert!(maze.failed_nets().is_empty());
+        assert_eq!(maze.grid.get((0, 2, 2)), Cell::Routed(2));
+        assert_eq!(maze.grid.get((0, 0, 2)), Cell::Routed(1));
+    }
+
+    #[test]
+    fn test_ripup_limit_reports_failed_net() {
+        let mut maze = single_layer_maze(4, 5, &[(1, 1), (1, 2), (1, 3), (3, 0), (3, 2), (3, 4)]);
+        maze.set_max_ripup_iterations(0);
+        let nets = vec![
+            net("net1", &[(0, 2, 0), (0, 2, 4)]),
+            net("net2", &[(0, 3, 1), (0, 3, 3)]),
+        ];
+        maze.process_nets(&nets).unwrap();
+
+        assert_eq!(maze.failed_nets(), vec!["net2"]);
+        // no start cells are left behind by the failed net
+        assert!(maze
+            .grid
+            .iter()
+            .all(|(_, cell)| !matches!(cell, Cell::Start(_))));
+    }
+
+    #[test]
+    fn test_negotiated_mode_resolves_shared_cells() {
+        // whichever net goes first, negotiation has to send net1 around through row 0
+        for reversed in [false, true] {
+            let mut maze =
+                single_layer_maze(4, 5, &[(1, 1), (1, 2), (1, 3), (3, 0), (3, 2), (3, 4)]);
+            maze.set_routing_mode(RoutingMode::Negotiated { max_iterations: 20 });
+            maze.set_max_ripup_iterations(0);
+            let mut nets = vec![
+                net("net1", &[(0, 2, 0), (0, 2, 4)]),
+                net("net2", &[(0, 3, 1), (0, 3, 3)]),
+            ];
+            if reversed {
+                nets.reverse();
+            }
+            maze.process_nets(&nets).unwrap();
+
+            // ids follow the input order, which is reversed half the time
+            assert!(maze.failed_nets().is_empty());
+            assert_eq!(maze.grid.get((0, 2, 2)), Cell::Routed(maze.net_ids["net2"]));
+            assert_eq!(maze.grid.get((0, 0, 2)), Cell::Routed(maze.net_ids["net1"]));
+        }
+    }
+
+    #[test]
+    fn test_negotiation_survives_many_iterations() {
+        // the nets have to cross on one layer, so the cell in the middle stays shared whatever
+        // they do and the present factor keeps growing until the last iteration
+        let mut maze = single_layer_maze(3, 3, &[]);
+        maze.set_routing_mode(RoutingMode::Negotiated { max_iterations: 40 });
+        maze.set_max_ripup_iterations(0);
+        let nets = vec![
+            net("a", &[(0, 1, 0), (0, 1, 2)]),
+            net("b", &[(0, 0, 1), (0, 2, 1)]),
+        ];
+        let result = maze.process_nets(&nets).unwrap();
+
+        // one net keeps the crossing after negotiation, the other cannot get around it
+        assert_eq!(result.failed_nets().len(), 1);
+        let routed: Vec<&str> = result
+            .nets
+            .iter()
+            .filter(|net| net.status == NetStatus::Routed)
+            .map(|net| net.name.as_str())
+            .collect();
+        assert_eq!(routed.len(), 1);
+        assert_eq!(
+            maze.grid.get((0, 1, 1)),
+            Cell::Routed(maze.net_ids[routed[0]])
+        );
+    }
+
+    #[test]
+    fn test_astar_matches_dijkstra_cost() {
+        let nets = vec![
+            net("net1", &[(0, 1, 0), (0, 6, 7)]),
+            net("net2", &[(1, 0, 2), (0, 7, 1)]),
+            net("net3", &[(1, 7, 7), (1, 0, 7)]),
+        ];
+        let obstacles = vec![
+            (0, 4, 4),
+            (0, 6, 5),
+            (0, 6, 4),
+            (1, 6, 6),
+            (1, 3, 1),
+            (1, 3, 2),
+        ];
+
+        let mut dijkstra = Maze::new(8, 8, 2, 10, 5);
+        dijkstra.initialize_obstacles(&obstacles);
+        dijkstra.process_nets(&nets).unwrap();
+
+        let mut astar = Maze::new(8, 8, 2, 10, 5);
+        astar.set_search_algorithm(SearchAlgorithm::AStar);
+        astar.initialize_obstacles(&obstacles);
+        astar.process_nets(&nets).unwrap();
+
+        for idx in 0..nets.len() {
+            assert_eq!(astar.routes[&idx].cost, dijkstra.routes[&idx].cost);
+        }
+        assert!(astar.expanded_cells() < dijkstra.expanded_cells());
+    }
+
+    #[test]
+    fn test_lower_bound_uses_via_and_direction_costs() {
+        let maze = Maze::new(8, 8, 2, 10, 5);
+        // along the rows on layer 0 it is cheaper to hop to layer 1 and back than to pay the penalty
+        assert_eq!(maze.lower_bound((0, 0, 0), (0, 7, 0)), 7 + 2 * 10);
+        assert_eq!(maze.lower_bound((0, 0, 0), (0, 2, 0)), 2 * 6);
+        assert_eq!(maze.lower_bound((0, 0, 0), (1, 3, 3)), 10 + 6);
+    }
+
+    #[test]
+    fn test_lower_bound_uses_via_costs_per_layer_pair() {
+        let mut maze = Maze::new(8, 8, 3, 10, 5);
+        maze.set_via_rules(ViaRules {
+            costs: vec![2, 30],
+            ..ViaRules::uniform(3, 10)
+        })
+        .unwrap();
+        // the cheap hop between the two lower layers dodges the penalty, the upper one does not pay
+        assert_eq!(maze.lower_bound((0, 0, 0), (0, 7, 0)), 7 + 2 * 2);
+        assert_eq!(maze.lower_bound((1, 0, 0), (1, 0, 7)), 7 + 2 * 2);
+        assert_eq!(maze.lower_bound((0, 0, 0), (2, 0, 0)), 32);
+        assert!(maze.set_via_rules(ViaRules::uniform(2, 10)).is_err());
+    }
+
+    #[test]
+    fn test_layer_stack_drives_step_costs() {
+        let mut maze = Maze::new(8, 8, 2, 10, 5);
+        maze.set_layer_stack(vec![
+            LayerSpec {
+                direction: PreferredDirection::None,
+                nonpreferred_cost: 5,
+                min_spacing: 0,
+            },
+            LayerSpec {
+                direction: PreferredDirection::Horizontal,
+                nonpreferred_cost: 2,
+                min_spacing: 0,
+            },
+        ])
+        .unwrap();
+        assert_eq!(maze.step_cost(0, true), 1);
+        assert_eq!(maze.step_cost(0, false), 1);
+        assert_eq!(maze.step_cost(1, true), 3);
+        assert_eq!(maze.step_cost(1, false), 1);
+        assert_eq!(maze.lower_bound((1, 0, 0), (1, 7, 0)), 7 * 3);
+    }
+
+    #[test]
+    fn test_steiner_topology_routes_through_steiner_point() {
+        // the three pins meet at the Steiner point (4, 2)
+        let nets = vec![net("net1", &[(0, 0, 2), (0, 4, 0), (0, 4, 4)])];
+
+        let mut maze = Maze::new(5, 5, 1, 10, 0);
+        maze.set_net_topology(NetTopology::Steiner);
+        maze.process_nets(&nets).unwrap();
+
+        assert!(maze.failed_nets().is_empty());
+        assert_eq!(maze.routes[&0].steiner_estimate, Some(8));
+        assert_eq!(maze.routes[&0].wirelength, 8);
+        assert_eq!(maze.grid.get((0, 4, 2)), Cell::Routed(1));
+    }
+
+    #[test]
+    fn test_source_strategies_pick_pins() {
+        let nets = vec![net("net1", &[(0, 2, 2), (0, 4, 4), (0, 1, 3), (0, 2, 0)])];
+        let mut maze = single_layer_maze(5, 5, &[(0, 0), (1, 0), (3, 0)]);
+        maze.assign_net_ids(&nets).unwrap();
+        maze.reserve_pins(&nets);
+
+        maze.set_source_strategy(SourceStrategy::FirstPin);
+        assert_eq!(maze.pick_source(&nets[0]), 0);
+        maze.set_source_strategy(SourceStrategy::NearestCorner);
+        assert_eq!(maze.pick_source(&nets[0]), 1);
+        maze.set_source_strategy(SourceStrategy::Centroid);
+        assert_eq!(maze.pick_source(&nets[0]), 0);
+        // (0,2,0) is boxed in by the edge and two obstacles
+        maze.set_source_strategy(SourceStrategy::MostConstrained);
+        assert_eq!(maze.pick_source(&nets[0]), 3);
+    }
+
+    #[test]
+    fn test_best_of_all_sources_is_never_worse() {
+        let nets = vec![net("net1", &[(0, 0, 2), (0, 4, 0), (0, 4, 4), (1, 2, 4)])];
+
+        let mut first = Maze::new(5, 5, 2, 10, 3);
+        first.process_nets(&nets).unwrap();
+        let mut best = Maze::new(5, 5, 2, 10, 3);
+        best.set_source_strategy(SourceStrategy::BestOfAll);
+        best.process_nets(&nets).unwrap();
+
+        assert!(best.total_cost() <= first.total_cost());
+        assert_eq!(best.routes[&0].source_strategy, SourceStrategy::BestOfAll);
+    }
+
+    #[test]
+    fn test_net_sequence_orders() {
+        let mut nets = vec![
+            net("net1", &[(0, 0, 0), (0, 5, 5)]),
+            net("net2", &[(0, 1, 1), (0, 1, 2), (0, 2, 1)]),
+            net("net3", &[(0, 0, 0), (0, 0, 3)]),
+        ];
+        nets[2].priority = 7;
+
+        assert_eq!(
+            Maze::net_sequence(&nets, &NetOrder::FileOrder),
+            vec![0, 1, 2]
+        );
+        assert_eq!(
+            Maze::net_sequence(&nets, &NetOrder::HalfPerimeter),
+            vec![1, 2, 0]
+        );
+        assert_eq!(
+            Maze::net_sequence(&nets, &NetOrder::PinCount),
+            vec![0, 2, 1]
+        );
+        assert_eq!(
+            Maze::net_sequence(&nets, &NetOrder::Priority),
+            vec![2, 0, 1]
+        );
+        assert_eq!(
+            Maze::net_sequence(&nets, &NetOrder::ShortestFirst),
+            vec![1, 2, 0]
+        );
+        assert_eq!(
+            Maze::net_sequence(&nets, &NetOrder::LongestFirst),
+            vec![0, 2, 1]
+        );
+    }
+
+    #[test]
+    fn test_best_of_orders_keeps_fewest_failures() {
+        let mut maze = single_layer_maze(4, 5, &[(1, 1), (1, 2), (1, 3), (3, 0), (3, 2), (3, 4)]);
+        maze.set_max_ripup_iterations(0);
+        maze.set_net_order(NetOrder::BestOf(vec![
+            NetOrder::FileOrder,
+            NetOrder::ShortestFirst,
+        ]));
+        let nets = vec![
+            net("net1", &[(0, 2, 0), (0, 2, 4)]),
+            net("net2", &[(0, 3, 1), (0, 3, 3)]),
+        ];
+        maze.process_nets(&nets).unwrap();
+
+        assert!(maze.failed_nets().is_empty());
+        assert_eq!(maze.grid.get((0, 2, 2)), Cell::Routed(2));
+    }
+
+    #[test]
+    fn test_unroutable_net_is_reported() {
+        let mut maze = single_layer_maze(3, 3, &[(0, 1), (1, 1), (2, 1)]);
+        let nets = vec![net("net1", &[(0, 0, 0), (0, 0, 2)])];
+        let result = maze.process_nets(&nets).unwrap();
+
+        assert_eq!(maze.failed_nets(), vec!["net1"]);
+        assert_eq!(result.nets[0].status, NetStatus::Failed);
+        assert_eq!(result.nets[0].cells, vec![(0, 0, 0)]);
+    }
+
+    #[test]
+    fn test_routing_result_describes_nets() {
+        let mut maze = single_layer_maze(3, 3, &[(0, 1), (1, 1), (2, 1)]);
+        let nets = vec![
+            net("net1", &[(0, 0, 0), (0, 2, 0), (0, 2, 2)]),
+            net("net2", &[(0, 0, 2), (0, 1, 2)]),
+        ];
+        let result = maze.process_nets(&nets).unwrap();
+
+        assert_eq!(result.nets[0].status, NetStatus::Partial);
+        assert_eq!(result.nets[0].cells, vec![(0, 0, 0), (0, 1, 0), (0, 2, 0)]);
+        assert_eq!(result.nets[0].segments.len(), 1);
+        assert_eq!(result.nets[0].wirelength, 2);
+        assert_eq!(result.nets[1].status, NetStatus::Routed);
+        assert_eq!(result.nets[1].cost, 1);
+        assert_eq!(result.failed_nets().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_nets_are_rejected() {
+        let mut maze = Maze::new(3, 3, 2, 10, 5);
+        let twice = vec![
+            net("clk", &[(0, 0, 0), (0, 2, 2)]),
+            net("clk", &[(0, 0, 2), (0, 2, 0)]),
+        ];
+        assert_eq!(
+            maze.process_nets(&twice).unwrap_err(),
+            RouterError::DuplicateNet {
+                net: "clk".to_string()
+            }
+        );
+        let unnamed = vec![net("", &[(0, 0, 0), (0, 2, 2)])];
+        assert!(matches!(
+            maze.process_nets(&unnamed),
+            Err(RouterError::InvalidNetName { .. })
+        ));
+
+        let off_grid = vec![net("net1", &[(0, 0, 0), (2, 1, 1)])];
+        assert_eq!(
+            maze.process_nets(&off_grid).unwrap_err(),
+            RouterError::PinOutOfRange {
+                net: "net1".to_string(),
+                coord: (2, 1, 1)
+            }
+        );
+
+        let overlapping = vec![
+            net("net1", &[(0, 0, 0), (0, 1, 1)]),
+            net("net2", &[(0, 1, 1), (0, 2, 2)]),
+        ];
+        assert_eq!(
+            maze.process_nets(&overlapping).unwrap_err(),
+            RouterError::OverlappingPins {
+                net: "net2".to_string(),
+                other: "net1".to_string(),
+                coord: (0, 1, 1)
+            }
+        );
+        let repeated = vec![net("net1", &[(0, 0, 0), (0, 2, 2), (0, 0, 0)])];
+        assert!(matches!(
+            maze.process_nets(&repeated),
+            Err(RouterError::OverlappingPins { .. })
+        ));
+
+        let empty = vec![net("net1", &[])];
+        assert!(matches!(
+            maze.process_nets(&empty),
+            Err(RouterError::EmptyNet { .. })
+        ));
+        assert!(maze.set_layer_stack(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_named_nets_beyond_255() {
+        let mut maze = single_layer_maze(300, 2, &[]);
+        let names: Vec<String> = (0..300).map(|i| format!("bus[{}]", i)).collect();
+        let nets: Vec<Net> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| net(name, &[(0, i, 0), (0, i, 1)]))
+            .collect();
+        let result = maze.process_nets(&nets).unwrap();
+
+        assert!(result.failed_nets().is_empty());
+        assert_eq!(maze.grid.get((0, 299, 1)), Cell::Routed(300));
+        assert_eq!(result.nets[299].name, "bus[299]");
+    }
+
+    #[test]
+    fn test_layers_show_net_names_that_fit() {
+        let mut maze = single_layer_maze(1, 3, &[]);
+        maze.process_nets(&[net("clk", &[(0, 0, 0), (0, 0, 2)])])
+            .unwrap();
+        assert_eq!(
+            maze.layers_side_by_side(),
+            "Maze Layers 1 & 2 (Side by Side)\n  S   clk  clk  │   #    #    #   │ Row 0\n"
+        );
+
+        let mut maze = single_layer_maze(1, 3, &[]);
+        maze.process_nets(&[net("clk_core", &[(0, 0, 0), (0, 0, 2)])])
+            .unwrap();
+        assert_eq!(
+            maze.layers_side_by_side(),
+            "Maze Layers 1 & 2 (Side by Side)\n S  1  1  │  #  #  #  │ Row 0\n  1 = clk_core\n"
+        );
+    }
+
+    #[test]
+    fn test_search_window_widens_until_routed() {
+        // the wall leaves only the last column free, outside the pins' bounding box
+        let wall = [(1, 0), (1, 1), (1, 2), (1, 3), (1, 4)];
+        let nets = vec![net("net1", &[(0, 0, 0), (0, 2, 0)])];
+
+        let mut unbounded = single_layer_maze(6, 6, &wall);
+        unbounded.process_nets(&nets).unwrap();
+        let mut windowed = single_layer_maze(6, 6, &wall);
+        windowed.set_search_margin(Some(0));
+        windowed.process_nets(&nets).unwrap();
+
+        assert!(windowed.failed_nets().is_empty());
+        assert_eq!(windowed.total_cost(), unbounded.total_cost());
+        assert_eq!(windowed.total_cost(), 12);
+    }
+
+    #[test]
+    fn test_search_window_limits_expansion() {
+        let nets = vec![net("net1", &[(0, 5, 5), (0, 5, 8)])];
+
+        let mut unbounded = single_layer_maze(20, 20, &[]);
+        unbounded.process_nets(&nets).unwrap();
+        let mut windowed = single_layer_maze(20, 20, &[]);
+        windowed.set_search_margin(Some(0));
+        windowed.process_nets(&nets).unwrap();
+
+        assert_eq!(windowed.total_cost(), 3);
+        assert!(windowed.expanded_cells() <= 4);
+        assert!(windowed.expanded_cells() < unbounded.expanded_cells());
+        assert_eq!(windowed.scratch.touched(), 0);
+    }
+
+    // Small three-pin nets spread over the grid, with a margin of 1 each is a group of its own
+    fn scattered_nets() -> Vec<Net> {
+        (0..12)
+            .map(|i| {
+                let (x, y) = (1 + (i % 4) * 7, 1 + (i / 4) * 9);
+                let name = format!("net{}", i + 1);
+                net(&name, &[(0, x, y), (1, x + 3, y + 4), (0, x + 1, y + 5)])
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let nets = scattered_nets();
+        // the nearest corner is one of the whole design's, not of a sub-grid's
+        let mut sequential = Maze::new(30, 30, 2, 10, 5);
+        sequential.set_source_strategy(SourceStrategy::NearestCorner);
+        let expected = sequential.process_nets(&nets).unwrap();
+        let sequence: Vec<usize> = (0..nets.len()).collect();
+        assert_eq!(sequential.independent_groups(&nets, &sequence, 1).len(), 12);
+        assert_eq!(sequential.independent_groups(&nets, &sequence, 3).len(), 1);
+
+        for threads in [1, 3] {
+            let mut parallel = Maze::new(30, 30, 2, 10, 5);
+            parallel.set_source_strategy(SourceStrategy::NearestCorner);
+            parallel.set_routing_mode(RoutingMode::Parallel { threads, margin: 1 });
+            let result = parallel.process_nets(&nets).unwrap();
+
+            assert_eq!(result.total_cost(), expected.total_cost());
+            for (net, expected) in result.nets.iter().zip(&expected.nets) {
+                assert_eq!(net.name, expected.name);
+                assert_eq!(net.cells, expected.cells);
+                assert_eq!(net.segments, expected.segments);
+            }
+            assert!(parallel.grid.iter().eq(sequential.grid.iter()));
+        }
+    }
+
+    #[test]
+    fn test_parallel_falls_back_outside_region() {
+        // net1 can only be routed around the wall, outside its region
+        let wall = [(1, 0), (1, 1), (1, 2), (1, 3), (1, 4)];
+        let nets = vec![
+            net("net1", &[(0, 0, 0), (0, 2, 0)]),
+            net("net2", &[(0, 5, 0), (0, 5, 2)]),
+        ];
+        let mut maze = single_layer_maze(6, 6, &wall);
+        maze.set_routing_mode(RoutingMode::Parallel {
+            threads: 2,
+            margin: 0,
+        });
+        let result = maze.process_nets(&nets).unwrap();
+
+        assert!(result.failed_nets().is_empty());
+        assert_eq!(result.nets[0].cost, 12);
+    }
+
+    #[test]
+    fn test_parallel_takes_cheaper_detour_outside_region() {
+        // inside its region net1 can only hop over the obstacle with two expensive vias, around
+        // it on the same layer is far cheaper
+        let nets = vec![
+            net("net1", &[(0, 2, 0), (0, 2, 4)]),
+            net("net2", &[(0, 5, 5), (0, 5, 3)]),
+        ];
+        let routed = |mode| {
+            let mut maze = Maze::new(6, 6, 2, 50, 0);
+            maze.initialize_obstacles(&vec![(0, 2, 2)]);
+            maze.set_routing_mode(mode);
+            maze.process_nets(&nets).unwrap()
+        };
+        let expected = routed(RoutingMode::Sequential);
+        let result = routed(RoutingMode::Parallel {
+            threads: 2,
+            margin: 0,
+        });
+
+        assert_eq!(expected.nets[0].cost, 6);
+        for (net, expected) in result.nets.iter().zip(&expected.nets) {
+            assert_eq!(net.cost, expected.cost);
+            assert_eq!(net.cells, expected.cells);
+        }
+    }
+
+    #[test]
+    fn test_parallel_matches_sequential_with_ripups() {
+        // net1 has to be ripped up and sent around through row 0 for net2, outside both regions.
+        // net3's first pin has more free neighbours on the whole grid than inside its region, so
+        // the most constrained pin is the second one only when the cells beyond are counted
+        let obstacles = [
+            (1, 1),
+            (1, 2),
+            (1, 3),
+            (3, 0),
+            (3, 2),
+            (3, 4),
+            (0, 8),
+            (2, 8),
+            (1, 7),
+        ];
+        let constrained = || net("net3", &[(0, 1, 12), (0, 1, 8)]);
+        let with_ripup = vec![
+            net("net1", &[(0, 2, 0), (0, 2, 4)]),
+            net("net2", &[(0, 3, 1), (0, 3, 3)]),
+            constrained(),
+        ];
+        for nets in [with_ripup, vec![constrained()]] {
+            let routed = |mode| {
+                let mut maze = single_layer_maze(4, 14, &obstacles);
+                maze.set_source_strategy(SourceStrategy::MostConstrained);
+                maze.set_routing_mode(mode);
+                maze.process_nets(&nets).unwrap()
+            };
+            let expected = routed(RoutingMode::Sequential);
+            let result = routed(RoutingMode::Parallel {
+                threads: 2,
+                margin: 0,
+            });
+
+            assert!(expected.failed_nets().is_empty());
+            assert_eq!(expected.nets.last().unwrap().cells[0], (0, 1, 8));
+            // only the work done differs, the sub-grids search fewer cells
+            assert_eq!(
+                RoutingResult {
+                    expanded_cells: expected.expanded_cells,
+                    ..result
+                },
+                expected
+            );
+        }
+    }
+
+    // Result of routing nets on a maze without direction penalties under the given policy
+    fn route_with(
+        tie_break: TieBreak,
+        (width, height, layers): (usize, usize, usize),
+        via_cost: u32,
+        obstacles: &[(usize, usize, usize)],
+        nets: &[Net],
+    ) -> RoutingResult {
+        let mut maze = Maze::new(width, height, layers, via_cost, 0);
+        maze.initialize_obstacles(&obstacles.to_vec());
+        maze.set_tie_break(tie_break);
+        maze.process_nets(nets).unwrap()
+    }
+
+    #[test]
+    fn test_fewest_vias_tie_break() {
+        // going around the obstacle costs as much as hopping over it on the other layer
+        let nets = vec![net("net1", &[(0, 0, 0), (0, 0, 4)])];
+        let route = |tie_break| route_with(tie_break, (5, 5, 2), 1, &[(0, 0, 2)], &nets);
+
+        let lexicographic = route(TieBreak::Lexicographic);
+        let fewest_vias = route(TieBreak::FewestVias);
+        assert_eq!(fewest_vias.total_cost(), lexicographic.total_cost());
+        assert!(!lexicographic.nets[0].vias.is_empty());
+        assert!(fewest_vias.nets[0].vias.is_empty());
+    }
+
+    #[test]
+    fn test_fewest_bends_tie_break() {
+        // both L shapes are blocked, the best any path can do is two bends
+        let nets = vec![net("net1", &[(0, 0, 0), (0, 4, 4)])];
+        let obstacles = [(0, 0, 2), (0, 3, 0)];
+        let route = |tie_break| route_with(tie_break, (5, 5, 1), 10, &obstacles, &nets);
+
+        let lexicographic = route(TieBreak::Lexicographic);
+        let fewest_bends = route(TieBreak::FewestBends);
+        assert_eq!(fewest_bends.total_cost(), lexicographic.total_cost());
+        assert_eq!(lexicographic.nets[0].segments.len(), 4);
+        assert_eq!(fewest_bends.nets[0].segments.len(), 3);
+    }
+
+    #[test]
+    fn test_random_tie_break_is_seeded() {
+        let nets = vec![net("net1", &[(0, 0, 0), (0, 3, 3)])];
+        let route = |seed| route_with(TieBreak::Random { seed }, (4, 4, 1), 10, &[], &nets);
+
+        let mut routes = HashSet::new();
+        for seed in 0..16 {
+            let result = route(seed);
+            assert_eq!(result.total_cost(), 6);
+            assert_eq!(result.nets[0].cells, route(seed).nets[0].cells);
+            routes.insert(result.nets[0].cells.clone());
+        }
+        assert!(routes.len() > 1);
+    }
+
+    // Layer stack without direction penalties, the given spacing on the first layer only
+    fn spaced_layers(layers: usize, spacing: usize) -> Vec<LayerSpec> {
+        (0..layers)
+            .map(|l| LayerSpec {
+                direction: PreferredDirection::None,
+                nonpreferred_cost: 0,
+                min_spacing: if l == 0 { spacing } else { 0 },
+            })
+            .collect()
+    }
+
+    // Two nets on neighbouring rows, their pins side by side
+    fn parallel_rows() -> Vec<Net> {
+        vec![
+            net("net1", &[(0, 3, 0), (0, 3, 6)]),
+            net("net2", &[(0, 4, 0), (0, 4, 6)]),
+        ]
+    }
+
+    #[test]
+    fn test_spacing_keeps_an_empty_track() {
+        let mut maze = single_layer_maze(7, 7, &[]);
+        maze.set_layer_stack(spaced_layers(2, 1)).unwrap();
+        let result = maze.process_nets(&parallel_rows()).unwrap();
+
+        assert!(result.failed_nets().is_empty());
+        assert!(result.violations.is_empty());
+        // neither may pass next to the other's pins, so both step a row away from each other
+        assert_eq!(result.nets[0].cost, 8);
+        assert_eq!(result.nets[1].cost, 8);
+        assert!((1..6).all(|y| maze.grid.get((0, 3, y)) == Cell::Free));
+        assert!((1..6).all(|y| maze.grid.get((0, 4, y)) == Cell::Free));
+        assert!((0..7).all(|y| maze.grid.get((0, 2, y)) == Cell::Routed(1)));
+        assert!((0..7).all(|y| maze.grid.get((0, 5, y)) == Cell::Routed(2)));
+    }
+
+    #[test]
+    fn test_spacing_violations_are_reported() {
+        let mut maze = single_layer_maze(7, 7, &[]);
+        maze.set_layer_stack(spaced_layers(2, 0)).unwrap();
+        maze.process_nets(&parallel_rows()).unwrap();
+        assert!(maze.routing_result().violations.is_empty());
+
+        // the rule tightened after routing, every pair of neighbouring cells but the two pin pairs
+        maze.set_layer_stack(spaced_layers(2, 1)).unwrap();
+        let violations = maze.routing_result().violations;
+        assert_eq!(violations.len(), 7 + 6 + 6 - 2);
+        assert_eq!(
+            violations[0],
+            SpacingViolation {
+                layer: 0,
+                nets: ("net1".to_string(), "net2".to_string()),
+                cells: ((0, 3, 0), (0, 4, 1)),
+            }
+        );
+        let pairs: HashSet<(Coord, Coord)> = violations.iter().map(|v| v.cells).collect();
+        assert_eq!(pairs.len(), violations.len());
+    }
+
+    #[test]
+    fn test_negotiated_mode_keeps_spacing() {
+        let mut maze = single_layer_maze(7, 7, &[]);
+        maze.set_layer_stack(spaced_layers(2, 1)).unwrap();
+        maze.set_routing_mode(RoutingMode::Negotiated { max_iterations: 5 });
+        let result = maze.process_nets(&parallel_rows()).unwrap();
+
+        assert!(result.failed_nets().is_empty());
+        assert!(result.violations.is_empty());
+    }
+
+    #[test]
+    fn test_spacing_joins_nearby_regions() {
+        // the regions are rows 0 to 1 and 3 to 5, with a free row between them
+        let nets = vec![
+            net("net1", &[(0, 0, 0), (0, 0, 2)]),
+            net("net2", &[(0, 4, 0), (0, 4, 2)]),
+        ];
+        let sequence = [0, 1];
+        let mut maze = single_layer_maze(6, 5, &[]);
+        assert_eq!(maze.independent_groups(&nets, &sequence, 1).len(), 2);
+        maze.set_layer_stack(spaced_layers(2, 1)).unwrap();
+        assert_eq!(maze.independent_groups(&nets, &sequence, 1).len(), 1);
+
+        maze.set_routing_mode(RoutingMode::Parallel {
+            threads: 2,
+            margin: 1,
+        });
+        let result = maze.process_nets(&nets).unwrap();
+        assert!(result.failed_nets().is_empty());
+        assert!(result.violations.is_empty());
+    }
+
+    // Maze without direction penalties where every via costs 1
+    fn via_maze(width: usize, height: usize, layers: usize, via_rules: ViaRules) -> Maze {
+        let mut maze = Maze::new(width, height, layers, 1, 0);
+        maze.set_layer_stack(spaced_layers(layers, 0)).unwrap();
+        maze.set_via_rules(via_rules).unwrap();
+        maze
+    }
+
+    #[test]
+    fn test_via_blockages_keep_wires() {
+        let mut maze = via_maze(3, 5, 2, ViaRules::uniform(2, 1));
+        maze.initialize_via_blockages(&[
+            Blockage {
+                name: None,
+                layer: Some(0),
+                rows: (1, 1),
+                cols: (0, 2),
+            },
+            Blockage::cell((1, 1, 4)),
+        ]);
+        let result = maze
+            .process_nets(&[net("net1", &[(0, 1, 0), (1, 1, 4)])])
+            .unwrap();
+
+        // the only straight route keeps its wires on the blocked cells and drops the via at y=3
+        assert_eq!(result.nets[0].cost, 5);
+        assert_eq!(
+            result.nets[0].vias,
+            vec![Via {
+                x: 1,
+                y: 3,
+                lower: 0,
+                upper: 1
+            }]
+        );
+        assert!(result.nets[0].cells.contains(&(0, 1, 1)));
+    }
+
+    #[test]
+    fn test_unstacked_vias_step_aside() {
+        let nets = [net("net1", &[(0, 2, 2), (2, 2, 2)])];
+        let mut stacked = via_maze(5, 5, 3, ViaRules::uniform(3, 1));
+        let result = stacked.process_nets(&nets).unwrap();
+        assert_eq!(result.nets[0].cost, 2);
+
+        let mut unstacked = via_maze(
+            5,
+            5,
+            3,
+            ViaRules {
+                stacking: false,
+                ..ViaRules::uniform(3, 1)
+            },
+        );
+        let result = unstacked.process_nets(&nets).unwrap();
+        let vias = &result.nets[0].vias;
+        assert_eq!(result.nets[0].cost, 4);
+        assert_eq!(vias.len(), 2);
+        assert_ne!((vias[0].x, vias[0].y), (vias[1].x, vias[1].y));
+    }
+
+    #[test]
+    fn test_via_spacing_between_nets() {
+        // both nets want a via at x=0 and x=1, net2 has to move its via two rows down
+        let nets = [
+            net("net1", &[(0, 0, 0), (1, 0, 0)]),
+            net("net2", &[(0, 1, 0), (1, 1, 0)]),
+        ];
+        let rules = ViaRules {
+            min_spacing: 1,
+            ..ViaRules::uniform(2, 1)
+        };
+        let mut maze = via_maze(3, 3, 2, rules);
+        let result = maze.process_nets(&nets).unwrap();
+
+        assert!(result.failed_nets().is_empty());
+        assert_eq!(result.nets[1].cost, 3);
+        assert_eq!((result.nets[1].vias[0].x, result.nets[1].vias[0].y), (2, 0));
+    }
+
+    #[test]
+    fn test_via_spacing_along_one_path() {
+        // a wall on the lower layer forces a hop over it, the way back down has to keep its distance
+        let wall = [(0, 0, 1), (0, 1, 1), (0, 2, 1)];
+        let nets = [net("net1", &[(0, 0, 0), (0, 0, 2)])];
+        for (spacing, cost) in [(0, 2 + 2), (2, 2 + 4)] {
+            let rules = ViaRules {
+                min_spacing: spacing,
+                ..ViaRules::uniform(2, 1)
+            };
+            let mut maze = via_maze(3, 5, 2, rules);
+            maze.initialize_obstacles(&wall.to_vec());
+            let result = maze.process_nets(&nets).unwrap();
+
+            let vias = &result.nets[0].vias;
+            assert_eq!(result.nets[0].cost, cost);
+            assert_eq!(vias.len(), 2);
+            assert!(
+                vias[0]
+                    .x
+                    .abs_diff(vias[1].x)
+                    .max(vias[0].y.abs_diff(vias[1].y))
+                    > spacing
+            );
+        }
+    }
+
+    #[test]
+    fn test_blockage_regions() {
+        let mut maze = Maze::new(6, 6, 3, 10, 0);
+        maze.initialize_blockages(&[
+            Blockage {
+                name: Some("ram0".to_string()),
+                layer: None,
+                rows: (1, 3),
+                cols: (1, 4),
+            },
+            Blockage {
+                name: None,
+                layer: Some(2),
+                rows: (4, 9),
+                cols: (0, 0),
+            },
+        ]);
+        let blocked: Vec<Coord> = maze
+            .grid
+            .iter()
+            .filter(|&(_, cell)| cell == Cell::Blocked)
+            .map(|(coord, _)| coord)
+            .collect();
+        assert_eq!(blocked.len(), 3 * 3 * 4 + 2);
+        assert!(blocked.contains(&(2, 5, 0)));
+        assert_eq!(maze.blockage_at((1, 3, 4)), Some("ram0"));
+        assert_eq!(maze.blockage_at((2, 4, 0)), None);
+
+        // the only way across is around the region
+        let result = maze
+            .process_nets(&[net("net1", &[(0, 2, 0), (0, 2, 5)])])
+            .unwrap();
+        assert!(result.failed_nets().is_empty());
+        assert!(result.nets[0]
+            .cells
+            .iter()
+            .all(|&cell| maze.blockage_at(cell).is_none()));
+    }
+
+    #[test]
+    fn test_any_cell_of_a_pin_shape_connects_it() {
+        // the second pin is a bar across row 4, its nearest end is four steps away
+        let bar = || Pin {
+            coord: (0, 4, 4),
+            shape: (0..4).map(|y| (0, 4, y)).collect(),
+        };
+        for mode in [
+            RoutingMode::Sequential,
+            RoutingMode::Parallel {
+                threads: 2,
+                margin: 1,
+            },
+        ] {
+            let nets = vec![
+                Net {
+                    _net_name: "net1".to_string(),
+                    pins: vec![Pin::at((0, 0, 0)), bar()],
+                    priority: 0,
+                },
+                net("net2", &[(0, 0, 4), (0, 2, 4)]),
+            ];
+            let mut maze = single_layer_maze(5, 5, &[]);
+            maze.set_routing_mode(mode);
+            let result = maze.process_nets(&nets).unwrap();
+
+            assert!(result.failed_nets().is_empty());
+            assert_eq!(result.nets[0].cost, 4);
+            assert!(bar()
+                .cells()
+                .all(|cell| result.nets[0].cells.contains(cell)));
+            assert!(bar()
+                .cells()
+                .all(|&cell| maze.grid.get(cell) == Cell::Routed(1)));
+        }
+    }
+
+    #[test]
+    fn test_multi_layer_pin_needs_no_via() {
+        let source = Pin {
+            coord: (0, 2, 2),
+            shape: vec![(1, 2, 2)],
+        };
+        let nets = vec![Net {
+            _net_name: "net1".to_string(),
+            pins: vec![source, Pin::at((1, 2, 4))],
+            priority: 0,
+        }];
+        let mut maze = Maze::new(5, 5, 2, 10, 0);
+        let result = maze.process_nets(&nets).unwrap();
+
+        assert_eq!(result.nets[0].status, NetStatus::Routed);
+        assert_eq!(result.nets[0].cost, 2);
+        assert!(result.nets[0].vias.is_empty());
+        assert_eq!(result.nets[0].cells[0], (0, 2, 2));
     }
 }
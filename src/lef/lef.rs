@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::{PreferredDirection, RouterError};
+
+// Top-level blocks the reader does not use and that close with END and their name, e.g. VIARULE
+// M2_M1 GENERATE ... END M2_M1. Their LAYER and VIA statements are not definitions
+static SKIPPED_NAMED_BLOCKS: [&str; 4] = ["VIARULE", "NONDEFAULTRULE", "SITE", "ARRAY"];
+
+// Top-level blocks the reader does not use and that close with END and their keyword
+static SKIPPED_BLOCKS: [&str; 6] = [
+    "UNITS",
+    "SPACING",
+    "PROPERTYDEFINITIONS",
+    "NOISETABLE",
+    "CORRECTIONTABLE",
+    "IRDROP",
+];
+
+// LEF and DEF are streams of whitespace separated tokens whose statements end in ';'. Semicolons
+// and parentheses are split off even when written against a word, '#' starts a comment and a
+// quoted string stays one token
+pub(crate) struct Tokens<'a> {
+    path: &'a str,
+    tokens: Vec<(&'a str, usize)>, // the token and the line it is on
+    at: usize,
+}
+
+impl<'a> Tokens<'a> {
+    pub(crate) fn new(path: &'a str, text: &'a str) -> Tokens<'a> {
+        let mut tokens = vec![];
+        for (number, line) in text.lines().enumerate() {
+            let mut start = None;
+            let mut quoted = false;
+            for (i, ch) in line.char_indices() {
+                if quoted {
+                    if ch == '"' {
+                        tokens.push((&line[start.unwrap()..=i], number + 1));
+                        start = None;
+                        quoted = false;
+                    }
+                    continue;
+                }
+                match ch {
+                    '"' if start.is_none() => {
+                        start = Some(i);
+                        quoted = true;
+                    }
+                    '#' | ';' | '(' | ')' => {
+                        if let Some(s) = start.take() {
+                            tokens.push((&line[s..i], number + 1));
+                        }
+                        if ch == '#' {
+                            break;
+                        }
+                        tokens.push((&line[i..i + 1], number + 1));
+                    }
+                    ch if ch.is_whitespace() => {
+                        if let Some(s) = start.take() {
+                            tokens.push((&line[s..i], number + 1));
+                        }
+                    }
+                    _ => {
+                        start.get_or_insert(i);
+                    }
+                }
+            }
+            if let Some(s) = start {
+                tokens.push((&line[s..], number + 1));
+            }
+        }
+        Tokens {
+            path,
+            tokens,
+            at: 0,
+        }
+    }
+
+    pub(crate) fn peek(&self) -> Option<&'a str> {
+        self.tokens.get(self.at).map(|&(token, _)| token)
+    }
+
+    pub(crate) fn next(&mut self) -> Option<&'a str> {
+        let token = self.peek()?;
+        self.at += 1;
+        Some(token)
+    }
+
+    // An error at the line of the token read last
+    pub(crate) fn error(&self, message: &'static str) -> RouterError {
+        let line = self
+            .tokens
+            .get(self.at.saturating_sub(1))
+            .map_or(0, |&(_, line)| line);
+        RouterError::DesignParse {
+            path: self.path.to_string(),
+            line,
+            message,
+        }
+    }
+
+    pub(crate) fn word(&mut self) -> Result<&'a str, RouterError> {
+        self.next()
+            .ok_or_else(|| self.error("Unexpected end of file"))
+    }
+
+    pub(crate) fn expect(&mut self, expected: &'static str) -> Result<(), RouterError> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(self.error(match expected {
+                ";" => "Expected ';'",
+                "(" => "Expected '('",
+                ")" => "Expected ')'",
+                _ => "Unexpected keyword",
+            })),
+        }
+    }
+
+    pub(crate) fn number<T: FromStr>(&mut self) -> Result<T, RouterError> {
+        self.word()?
+            .parse()
+            .map_err(|_| self.error("Expected a number"))
+    }
+
+    // ( x y ) in database units
+    pub(crate) fn point(&mut self) -> Result<(i64, i64), RouterError> {
+        self.expect("(")?;
+        let point = (self.number()?, self.number()?);
+        self.expect(")")?;
+        Ok(point)
+    }
+
+    // Everything up to and including the next ';'
+    pub(crate) fn skip_statement(&mut self) {
+        while let Some(token) = self.next() {
+            if token == ";" {
+                return;
+            }
+        }
+    }
+
+    // Everything up to and including END name
+    pub(crate) fn skip_to_end(&mut self, name: &str) {
+        while let Some(token) = self.next() {
+            if token == "END" && self.peek() == Some(name) {
+                self.next();
+                return;
+            }
+        }
+    }
+}
+
+// LEF lengths are in microns, a rectangle is (x1, y1, x2, y2)
+pub type MicronRect = (f64, f64, f64, f64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LayerKind {
+    Routing,
+    Cut,   // between two routing layers, where vias are
+    Other, // masterslice, overlap and the like, the router ignores them
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LefLayer {
+    pub name: String,
+    pub kind: LayerKind,
+    pub direction: PreferredDirection,
+    pub pitch: Option<(f64, f64)>, // along x and along y
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LefVia {
+    pub name: String,
+    pub default: bool,
+    pub layers: Vec<String>, // the layers it has geometry on, routing and cut
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    pub size: (f64, f64),
+    pub pins: HashMap<String, Vec<(String, MicronRect)>>, // the port rectangles of each pin, with their layer
+}
+
+// The parts of a LEF library the router uses, everything else is skipped
+#[derive(Debug, Clone, Default)]
+pub struct Lef {
+    pub layers: Vec<LefLayer>, // bottom up, as listed
+    pub vias: Vec<LefVia>,
+    pub macros: HashMap<String, Macro>,
+}
+
+impl Lef {
+    pub fn parse(path: &str, text: &str) -> Result<Lef, RouterError> {
+        let mut tokens = Tokens::new(path, text);
+        let mut lef = Lef::default();
+        while let Some(token) = tokens.next() {
+            match token {
+                "LAYER" => lef.layers.push(Self::parse_layer(&mut tokens)?),
+                "VIA" => lef.vias.push(Self::parse_via(&mut tokens)?),
+                "MACRO" => {
+                    let name = tokens.word()?;
+                    let parsed = Self::parse_macro(&mut tokens, name)?;
+                    lef.macros.insert(name.to_string(), parsed);
+                }
+                "END" => {
+                    if tokens.next() == Some("LIBRARY") {
+                        break;
+                    }
+                }
+                block if SKIPPED_NAMED_BLOCKS.contains(&block) => {
+                    let name = tokens.word()?;
+                    tokens.skip_to_end(name);
+                }
+                block if SKIPPED_BLOCKS.contains(&block) => tokens.skip_to_end(block),
+                _ => tokens.skip_statement(),
+            }
+        }
+        Ok(lef)
+    }
+
+    fn parse_layer(tokens: &mut Tokens) -> Result<LefLayer, RouterError> {
+        let mut layer = LefLayer {
+            name: tokens.word()?.to_string(),
+            kind: LayerKind::Other,
+            direction: PreferredDirection::None,
+            pitch: None,
+        };
+        loop {
+            match tokens.word()? {
+                "END" => {
+                    if tokens.word()? != layer.name {
+                        return Err(tokens.error("END does not match the LAYER name"));
+                    }
+                    return Ok(layer);
+                }
+                "TYPE" => {
+                    layer.kind = match tokens.word()? {
+                        "ROUTING" => LayerKind::Routing,
+                        "CUT" => LayerKind::Cut,
+                        _ => LayerKind::Other,
+                    };
+                    tokens.expect(";")?;
+                }
+                "DIRECTION" => {
+                    layer.direction = match tokens.word()? {
+                        "HORIZONTAL" => PreferredDirection::Horizontal,
+                        "VERTICAL" => PreferredDirection::Vertical,
+                        // the grid has no diagonal steps, either way costs the same
+                        "DIAG45" | "DIAG135" => PreferredDirection::None,
+                        _ => return Err(tokens.error("Unknown layer DIRECTION")),
+                    };
+                    tokens.expect(";")?;
+                }
+                "PITCH" => {
+                    let x: f64 = tokens.number()?;
+                    let y = match tokens.peek() {
+                        Some(";") => x,
+                        _ => tokens.number()?,
+                    };
+                    layer.pitch = Some((x, y));
+                    tokens.expect(";")?;
+                }
+                _ => tokens.skip_statement(),
+            }
+        }
+    }
+
+    fn parse_via(tokens: &mut Tokens) -> Result<LefVia, RouterError> {
+        let mut via = LefVia {
+            name: tokens.word()?.to_string(),
+            default: false,
+            layers: vec![],
+        };
+        if tokens.peek() == Some("DEFAULT") {
+            tokens.next();
+            via.default = true;
+        }
+        loop {
+            match tokens.word()? {
+                "END" => {
+                    tokens.word()?;
+                    return Ok(via);
+                }
+                "LAYER" => {
+                    via.layers.push(tokens.word()?.to_string());
+                    tokens.skip_statement();
+                }
+                ";" => {}
+                _ => tokens.skip_statement(),
+            }
+        }
+    }
+
+    fn parse_macro(tokens: &mut Tokens, name: &str) -> Result<Macro, RouterError> {
+        let mut parsed = Macro {
+            size: (0.0, 0.0),
+            pins: HashMap::new(),
+        };
+        loop {
+            match tokens.word()? {
+                "END" if tokens.peek() == Some(name) => {
+                    tokens.next();
+                    return Ok(parsed);
+                }
+                "SIZE" => {
+                    let width = tokens.number()?;
+                    tokens.expect("BY")?;
+                    parsed.size = (width, tokens.number()?);
+                    tokens.expect(";")?;
+                }
+                "PIN" => {
+                    let pin = tokens.word()?;
+                    let ports = Self::parse_pin(tokens, pin)?;
+                    parsed.pins.insert(pin.to_string(), ports);
+                }
+                "OBS" => while tokens.word()? != "END" {},
+                _ => tokens.skip_statement(),
+            }
+        }
+    }
+
+    // The rectangles of every PORT of a macro pin
+    fn parse_pin(
+        tokens: &mut Tokens,
+        name: &str,
+    ) -> Result<Vec<(String, MicronRect)>, RouterError> {
+        let mut rects = vec![];
+        loop {
+            match tokens.word()? {
+                "END" if tokens.peek() == Some(name) => {
+                    tokens.next();
+                    return Ok(rects);
+                }
+                "PORT" => {
+                    let mut layer = None;
+                    loop {
+                        match tokens.word()? {
+                            "END" => break,
+                            "LAYER" => {
+                                layer = Some(tokens.word()?.to_string());
+                                tokens.skip_statement();
+                            }
+                            "RECT" => {
+                                if tokens.peek() == Some("MASK") {
+                                    tokens.next();
+                                    tokens.next();
+                                }
+                                let rect = (
+                                    tokens.number()?,
+                                    tokens.number()?,
+                                    tokens.number()?,
+                                    tokens.number()?,
+                                );
+                                tokens.expect(";")?;
+                                let layer = layer
+                                    .clone()
+                                    .ok_or_else(|| tokens.error("RECT before any LAYER"))?;
+                                rects.push((layer, rect));
+                            }
+                            "POLYGON" => {
+                                return Err(tokens.error("POLYGON port shapes are not supported"))
+                            }
+                            _ => tokens.skip_statement(),
+                        }
+                    }
+                }
+                _ => tokens.skip_statement(),
+            }
+        }
+    }
+
+    pub fn routing_layers(&self) -> impl Iterator<Item = &LefLayer> {
+        self.layers
+            .iter()
+            .filter(|layer| layer.kind == LayerKind::Routing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lef::lef::{LayerKind, Lef, Tokens};
+    use crate::{PreferredDirection, RouterError};
+
+    static LIBRARY: &str = include_str!("../../test_cases/design1.lef");
+
+    #[test]
+    fn test_tokens() {
+        let mut tokens = Tokens::new(
+            "t.def",
+            "DESIGN top;# comment ( x )\n- a+b (1 -2) \"q r\" ;",
+        );
+        let mut all = vec![];
+        while let Some(token) = tokens.next() {
+            all.push(token);
+        }
+        assert_eq!(
+            all,
+            vec!["DESIGN", "top", ";", "-", "a+b", "(", "1", "-2", ")", "\"q r\"", ";"]
+        );
+        assert_eq!(
+            tokens.error("Expected ';'"),
+            RouterError::DesignParse {
+                path: "t.def".to_string(),
+                line: 2,
+                message: "Expected ';'"
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_library() {
+        let lef = Lef::parse("lib.lef", LIBRARY).unwrap();
+        let names: Vec<&str> = lef.layers.iter().map(|layer| layer.name.as_str()).collect();
+        assert_eq!(names, vec!["metal1", "via1", "metal2"]);
+        assert_eq!(lef.layers[1].kind, LayerKind::Cut);
+        assert_eq!(lef.layers[0].direction, PreferredDirection::Horizontal);
+        assert_eq!(lef.layers[2].direction, PreferredDirection::Vertical);
+        assert_eq!(lef.layers[2].pitch, Some((0.2, 0.2)));
+        assert_eq!(lef.routing_layers().count(), 2);
+
+        assert_eq!(lef.vias.len(), 1);
+        assert!(lef.vias[0].default);
+        assert_eq!(lef.vias[0].layers, vec!["metal1", "via1", "metal2"]);
+
+        let inv = &lef.macros["INV"];
+        assert_eq!(inv.size, (0.6, 1.0));
+        assert_eq!(
+            inv.pins["A"],
+            vec![("metal1".to_string(), (0.05, 0.25, 0.15, 0.35))]
+        );
+        assert_eq!(inv.pins["Y"].len(), 2);
+
+        for diagonal in ["DIAG45", "DIAG135"] {
+            let text = format!(
+                "LAYER m1\n TYPE ROUTING ;\n DIRECTION {} ;\nEND m1\n",
+                diagonal
+            );
+            let lef = Lef::parse("lib.lef", &text).unwrap();
+            assert_eq!(lef.layers[0].direction, PreferredDirection::None);
+        }
+    }
+
+    #[test]
+    fn test_parse_library_errors() {
+        let err = Lef::parse("bad.lef", "LAYER m1\n TYPE ROUTING ;\n PITCH x ;\nEND m1\n");
+        assert_eq!(
+            err.unwrap_err(),
+            RouterError::DesignParse {
+                path: "bad.lef".to_string(),
+                line: 3,
+                message: "Expected a number"
+            }
+        );
+        assert!(Lef::parse("bad.lef", "LAYER m1\n TYPE ROUTING ;\nEND m2\n").is_err());
+        assert!(Lef::parse("bad.lef", "MACRO INV\n SIZE 1 BY").is_err());
+        let polygon = LIBRARY.replace("RECT 0.05 0.25 0.15 0.35", "POLYGON 0 0 0.1 0 0.1 0.1");
+        assert!(matches!(
+            Lef::parse("lib.lef", &polygon),
+            Err(RouterError::DesignParse {
+                message: "POLYGON port shapes are not supported",
+                ..
+            })
+        ));
+    }
+}
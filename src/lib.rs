@@ -1,13 +1,36 @@
 pub mod config;
+pub mod def;
+pub mod design;
+pub mod error;
+pub mod grid;
+pub mod json;
 pub mod lee_maze;
+pub mod lef;
+pub mod raster;
+pub mod routing_result;
+pub mod solution;
+pub mod steiner;
+pub mod svg;
 
 pub use config::config::Config;
-pub use lee_maze::lee_maze::Maze;
+pub use design::design::{Design, GridMapping};
+pub use error::error::RouterError;
+pub use json::json::to_json;
+pub use lee_maze::lee_maze::{
+    Maze, NetOrder, NetTopology, RoutingMode, SearchAlgorithm, SourceStrategy, TieBreak,
+};
+pub use raster::raster::{rasterize, Raster};
+pub use routing_result::routing_result::{
+    NetResult, NetStatus, RoutingResult, Segment, SpacingViolation, Via,
+};
+pub use solution::solution::{parse_solution, read_solution, to_solution};
+pub use svg::svg::{to_svg, SvgView};
 
 use std::path::Path;
 
 static USAGE_MSG: &str = r#"
-Usage: cargo run -- <input_file>
+Usage: cargo run -- <input_file> [options]
+       cargo run -- <design.def> --lef <library.lef> [options]
 
 Description:
   This program implements the Lee algorithm for maze routing.
@@ -15,35 +38,169 @@ Description:
 
 Arguments:
   <input_file>    The input file containing the maze layout.
+  <design.def>    A DEF design, read with the LEF library given by --lef.
+
+Options:
+  --json <file>          Also write the routing result as JSON to <file>.
+  --svg <file>           Also draw every layer side by side as SVG to <file>.
+  --svg-overlay <file>   Also draw all layers in one SVG picture to <file>.
+  --ppm <file>           Also write the grid as a PPM image to <file>.
+  --png <file>           Also write the grid as a PNG image to <file>.
+  --scale <n>            Pixels per cell side in PPM and PNG images, 1 by default.
+  --def <file>           Also write the routed wires as a DEF design to <file>.
+  --solution <file>      Also write every routed wire as a net line of cells to <file>.
+  --load-solution <file> Lay the wires saved with --solution instead of routing.
+  --origin <x,y>         Database X and Y of grid cell (0,0) for a text input, 0,0 by default.
+  --pitch <x,y>          Database units between grid tracks along X and Y for a text
+                         input, or one number for both, 1 by default.
 
 Example:
-  cargo run -- maze.txt
+  cargo run -- maze.txt --json maze.json
 "#;
 
-pub fn usage(args: &[String]) -> Result<&str, &'static str> {
+pub fn usage(args: &[String]) -> Result<&str, RouterError> {
     match args.get(1) {
         Some(filename) => {
-            if !filename.ends_with(".txt") {
-                return Err("Unsupported file");
+            if !filename.ends_with(".txt") && !filename.ends_with(".def") {
+                return Err(RouterError::Usage("Unsupported file"));
             }
             if !Path::new(filename).exists() {
-                return Err("File does not exist in the current directory");
+                return Err(RouterError::Usage(
+                    "File does not exist in the current directory",
+                ));
             }
             Ok(filename)
         }
-        None => Err(USAGE_MSG),
+        None => Err(RouterError::Usage(USAGE_MSG)),
+    }
+}
+
+// The value given after an option such as --json, if the option is there
+pub fn option_value<'a>(args: &'a [String], option: &str) -> Option<&'a str> {
+    args.get(2..)?
+        .windows(2)
+        .find(|pair| pair[0] == option)
+        .map(|pair| pair[1].as_str())
+}
+
+// A value such as --pitch 200,100, or a single number for both halves
+pub fn parse_pair(value: &str) -> Option<(i64, i64)> {
+    match value.split_once(',') {
+        Some((a, b)) => Some((a.trim().parse().ok()?, b.trim().parse().ok()?)),
+        None => value.trim().parse().ok().map(|n| (n, n)),
     }
 }
 
 #[derive(Debug)]
 pub struct Pin {
-    pub coord: Coord,
+    pub coord: Coord,      // where the pin is reported, the first cell of its shape
+    pub shape: Vec<Coord>, // the other cells the pin covers, reaching any of them connects it
+}
+
+impl Pin {
+    // A pin of a single cell
+    pub fn at(coord: Coord) -> Pin {
+        Pin {
+            coord,
+            shape: vec![],
+        }
+    }
+
+    // Every cell of the pin, coord first
+    pub fn cells(&self) -> impl Iterator<Item = &Coord> {
+        std::iter::once(&self.coord).chain(&self.shape)
+    }
 }
 
 #[derive(Debug)]
 pub struct Net {
     _net_name: String,
     pins: Vec<Pin>,
+    priority: u32, // higher is routed earlier with NetOrder::Priority
+}
+
+impl Net {
+    // Every cell of every pin of the net
+    fn pin_cells(&self) -> impl Iterator<Item = &Coord> {
+        self.pins.iter().flat_map(|pin| pin.cells())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreferredDirection {
+    Horizontal, // along a row, the column changes
+    Vertical,   // along a column, the row changes
+    None,       // both directions cost the same
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerSpec {
+    pub direction: PreferredDirection,
+    pub nonpreferred_cost: u32, // added to every step against the preferred direction
+    pub min_spacing: usize, // empty cells required between wires of different nets, 0 allows touching
+}
+
+impl LayerSpec {
+    // Horizontal on the first layer, then alternating
+    pub fn alternating(layers: usize, nonpreferred_cost: u32) -> Vec<LayerSpec> {
+        (0..layers)
+            .map(|l| LayerSpec {
+                direction: if l % 2 == 0 {
+                    PreferredDirection::Horizontal
+                } else {
+                    PreferredDirection::Vertical
+                },
+                nonpreferred_cost,
+                min_spacing: 0,
+            })
+            .collect()
+    }
+}
+
+// Rectangle of blocked cells, both corners included, on one layer or on every layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Blockage {
+    pub name: Option<String>, // set for the regions of a BLOCKAGE line
+    pub layer: Option<usize>, // None covers every layer
+    pub rows: (usize, usize), // first and last x
+    pub cols: (usize, usize), // first and last y
+}
+
+impl Blockage {
+    // A single cell, as an OBS (layer,x,y) line gives it
+    pub fn cell((l, x, y): Coord) -> Blockage {
+        Blockage {
+            name: None,
+            layer: Some(l),
+            rows: (x, x),
+            cols: (y, y),
+        }
+    }
+
+    pub fn contains(&self, (l, x, y): Coord) -> bool {
+        self.layer.is_none_or(|layer| layer == l)
+            && (self.rows.0..=self.rows.1).contains(&x)
+            && (self.cols.0..=self.cols.1).contains(&y)
+    }
+}
+
+// Rules every via has to follow, a via joins the same x,y on two neighbouring layers
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViaRules {
+    pub stacking: bool, // whether a via may continue straight on through the next layer
+    pub min_spacing: usize, // empty positions required between vias joining the same two layers
+    pub costs: Vec<u32>, // costs[l] is the cost of a via between layers l and l + 1
+}
+
+impl ViaRules {
+    // Stacking allowed, no spacing and the same cost between every two layers
+    pub fn uniform(layers: usize, via_cost: u32) -> ViaRules {
+        ViaRules {
+            stacking: true,
+            min_spacing: 0,
+            costs: vec![via_cost; layers.saturating_sub(1)],
+        }
+    }
 }
 
-type Coord = (usize, usize, usize); // layer,x,y
+pub type Coord = (usize, usize, usize); // layer,x,y
@@ -1,8 +1,12 @@
 use std::env;
+use std::fs;
 use std::path::Path;
 use std::process;
 
-use mazerouting_lee::{usage, Config, Maze};
+use mazerouting_lee::{
+    option_value, parse_pair, rasterize, read_solution, to_json, to_solution, to_svg, usage,
+    Config, Design, GridMapping, Maze, RouterError, SvgView,
+};
 fn main() {
     let args: Vec<String> = env::args().collect();
 
@@ -14,23 +18,109 @@ fn main() {
         }
     };
 
-    let config = Config::build(filename).unwrap_or_else(|err_msg| {
+    let design = if filename.ends_with(".def") {
+        let lef = option_value(&args, "--lef").unwrap_or_else(|| {
+            eprint!(
+                "{}",
+                RouterError::Usage("A DEF design needs its LEF library, given with --lef <file>")
+            );
+            process::exit(1);
+        });
+        Design::read(lef, filename)
+    } else {
+        // a text input has no database units of its own, the grid is laid on
+        // the origin and pitch given on the command line
+        let pair = |option, default, message| match option_value(&args, option) {
+            Some(value) => parse_pair(value).unwrap_or_else(|| {
+                eprint!("{}", RouterError::Usage(message));
+                process::exit(1);
+            }),
+            None => default,
+        };
+        let mapping = GridMapping {
+            origin: pair("--origin", (0, 0), "--origin takes X,Y in database units"),
+            pitch: pair("--pitch", (1, 1), "--pitch takes X,Y in database units"),
+        };
+        if mapping.pitch.0 <= 0 || mapping.pitch.1 <= 0 {
+            eprint!("{}", RouterError::Usage("--pitch must be positive"));
+            process::exit(1);
+        }
+        let name = Path::new(filename)
+            .file_stem()
+            .map_or("design".into(), |stem| stem.to_string_lossy());
+        Config::build(filename).map(|config| Design::from_config(&name, config, mapping))
+    }
+    .unwrap_or_else(|err_msg| {
         eprint!("{}", err_msg);
         process::exit(1);
     });
+    let config = &design.config;
+
+    // Initialize maze based on the config, with its obstacles
+    let mut maze = Maze::from_config(config).unwrap_or_else(|err| {
+        eprint!("{}", err);
+        process::exit(1);
+    });
 
-    // Initialize maze based on the config
-    let mut maze = Maze::new(
-        config.grid_width as usize,
-        config.grid_height as usize,
-        2,
-        config.via_cost as u32,
-        config.nonpreferred_direction_cost as u32,
-    );
+    // Process the nets, or lay the routes of a saved solution instead
+    let result = match option_value(&args, "--load-solution") {
+        Some(path) => read_solution(path).and_then(|paths| maze.load_routes(&config.nets, &paths)),
+        None => maze.process_nets(&config.nets),
+    }
+    .unwrap_or_else(|err| {
+        eprint!("{}", err);
+        process::exit(1);
+    });
 
-    // Add obstacles to the maze
-    maze.initialize_obstacles(&config.obstacles);
+    for net in result.failed_nets() {
+        eprintln!("Net {} could not be routed ({:?})", net.name, net.status);
+    }
+    println!("\nFinal Layout");
+    maze.print_layers_side_by_side();
+    result.print_report();
 
-    // Process the nets
-    maze.process_nets(&config.nets);
+    let scale = match option_value(&args, "--scale") {
+        Some(scale) => scale
+            .parse()
+            .ok()
+            .filter(|&scale| scale > 0)
+            .unwrap_or_else(|| {
+                eprint!(
+                    "{}",
+                    RouterError::Usage("--scale takes a positive number of pixels per cell")
+                );
+                process::exit(1);
+            }),
+        None => 1,
+    };
+    for option in [
+        "--json",
+        "--svg",
+        "--svg-overlay",
+        "--ppm",
+        "--png",
+        "--def",
+        "--solution",
+    ] {
+        let Some(path) = option_value(&args, option) else {
+            continue;
+        };
+        let contents = match option {
+            "--json" => to_json(&maze, &result).into_bytes(),
+            "--svg" => to_svg(&maze, &result, SvgView::Layers).into_bytes(),
+            "--svg-overlay" => to_svg(&maze, &result, SvgView::Overlay).into_bytes(),
+            "--ppm" => rasterize(&maze, scale).to_ppm(),
+            "--png" => rasterize(&maze, scale).to_png(),
+            "--def" => design.to_def(&result).into_bytes(),
+            _ => to_solution(&result).into_bytes(),
+        };
+        fs::write(path, contents).unwrap_or_else(|err| {
+            let err = RouterError::Output {
+                path: path.to_string(),
+                message: err.to_string(),
+            };
+            eprint!("{}", err);
+            process::exit(1);
+        });
+    }
 }
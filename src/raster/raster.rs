@@ -0,0 +1,300 @@
+use crate::grid::grid::Cell;
+use crate::Maze;
+
+static FREE_COLOR: [u8; 3] = [255, 255, 255];
+static OBSTACLE_COLOR: [u8; 3] = [136, 136, 136];
+static VIA_COLOR: [u8; 3] = [0, 0, 0];
+static GAP_COLOR: [u8; 3] = [208, 208, 208]; // the column of cells between two layers
+
+static PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+static MAX_STORED_BLOCK: usize = 65535; // bytes in one uncompressed deflate block
+
+// RGB image, three bytes per pixel, row after row from the top
+#[derive(Clone, Debug, PartialEq)]
+pub struct Raster {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+// The net with this id in the colour svg::net_color gives it, hues a golden angle apart
+pub fn net_rgb(id: u32) -> [u8; 3] {
+    let hue = ((id as f64 - 1.0) * 137.508) % 360.0 / 60.0;
+    let (saturation, lightness) = (0.7, 0.45);
+    let chroma = (1.0 - (2.0 * lightness - 1.0_f64).abs()) * saturation;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    [r, g, b].map(|v| ((v + m) * 255.0).round() as u8)
+}
+
+// The grid as an image, laid out like print_layers_side_by_side: rows (x) run down, columns (y)
+// across and the layers sit side by side, one cell apart. Every cell is a scale x scale square
+pub fn rasterize(maze: &Maze, scale: usize) -> Raster {
+    let layers = maze.layer_stack().len();
+    let columns = layers * (maze.height() + 1) - 1;
+    let width = columns * scale;
+    let height = maze.width() * scale;
+
+    let mut pixels = Vec::with_capacity(width * height * 3);
+    let mut row = Vec::with_capacity(width * 3);
+    for r in 0..maze.width() {
+        row.clear();
+        for l in 0..layers {
+            if l > 0 {
+                for _ in 0..scale {
+                    row.extend_from_slice(&GAP_COLOR);
+                }
+            }
+            for c in 0..maze.height() {
+                let coord = (l, r, c);
+                let color = if maze.is_via(coord) {
+                    VIA_COLOR
+                } else {
+                    match maze.cell(coord) {
+                        Cell::Blocked => OBSTACLE_COLOR,
+                        Cell::Routed(net) | Cell::Start(net) => net_rgb(net),
+                        Cell::Free | Cell::Target => FREE_COLOR,
+                    }
+                };
+                for _ in 0..scale {
+                    row.extend_from_slice(&color);
+                }
+            }
+        }
+        for _ in 0..scale {
+            pixels.extend_from_slice(&row);
+        }
+    }
+    Raster {
+        width,
+        height,
+        pixels,
+    }
+}
+
+impl Raster {
+    // Binary PPM (P6)
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let mut ppm = format!("P6\n{} {}\n255\n", self.width, self.height).into_bytes();
+        ppm.extend_from_slice(&self.pixels);
+        ppm
+    }
+
+    // 8-bit RGB PNG whose image data is stored in uncompressed deflate blocks, so no
+    // compressor is needed. Every scanline starts with filter type 0 (none)
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        header.extend_from_slice(&[8, 2, 0, 0, 0]); // bit depth, truecolour, deflate, no filter, no interlace
+
+        let mut scanlines = Vec::with_capacity(self.height * (self.width * 3 + 1));
+        for line in self.pixels.chunks((self.width * 3).max(1)) {
+            scanlines.push(0);
+            scanlines.extend_from_slice(line);
+        }
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        write_chunk(&mut png, b"IDAT", &zlib_stored(&scanlines));
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+}
+
+// Length, type, data and the CRC of type and data
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+// A zlib stream of stored (uncompressed) deflate blocks followed by the Adler-32 of the data
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut stream = Vec::with_capacity(data.len() + blocks * 5 + 6);
+    stream.extend_from_slice(&[0x78, 0x01]); // 32K window, no preset dictionary
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if chunks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]); // a single empty final block
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        stream.push(last as u8); // BFINAL, then block type 00
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(chunk);
+    }
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+// CRC-32 as PNG defines it (polynomial 0xedb88320, reflected)
+fn crc32(data: &[u8]) -> u32 {
+    let mut table = [0u32; 256];
+    for (n, entry) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 == 1 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        *entry = c;
+    }
+    let mut crc = u32::MAX;
+    for &byte in data {
+        crc = table[((crc ^ byte as u32) & 0xff) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 bytes is the most that can be summed before b may overflow
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::raster::raster::{
+        adler32, crc32, net_rgb, rasterize, zlib_stored, Raster, GAP_COLOR, OBSTACLE_COLOR,
+        VIA_COLOR,
+    };
+    use crate::{Maze, Net, Pin};
+
+    // The data of a zlib stream made only of stored blocks
+    fn inflate_stored(stream: &[u8]) -> Vec<u8> {
+        assert_eq!(&stream[..2], &[0x78, 0x01]);
+        let mut data = vec![];
+        let mut at = 2;
+        loop {
+            let last = stream[at] & 1 == 1;
+            assert_eq!(stream[at] >> 1, 0);
+            let len = u16::from_le_bytes([stream[at + 1], stream[at + 2]]) as usize;
+            let nlen = u16::from_le_bytes([stream[at + 3], stream[at + 4]]);
+            assert_eq!(nlen, !(len as u16));
+            data.extend_from_slice(&stream[at + 5..at + 5 + len]);
+            at += 5 + len;
+            if last {
+                break;
+            }
+        }
+        let adler = u32::from_be_bytes(stream[at..at + 4].try_into().unwrap());
+        assert_eq!(adler, adler32(&data));
+        assert_eq!(at + 4, stream.len());
+        data
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+        assert_eq!(adler32(&[]), 1);
+    }
+
+    #[test]
+    fn test_stored_blocks_round_trip() {
+        for len in [0, 1, 65535, 65536, 200_000] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 7 % 251) as u8).collect();
+            assert_eq!(inflate_stored(&zlib_stored(&data)), data);
+        }
+    }
+
+    #[test]
+    fn test_rasterize_and_encode() {
+        // a net on layer 1 that climbs to layer 2 to get past the wall at column 2
+        let mut maze = Maze::new(2, 4, 2, 1, 10);
+        maze.initialize_obstacles(&vec![(0, 0, 2), (0, 1, 2), (1, 1, 0)]);
+        let nets = vec![Net {
+            _net_name: "clk".to_string(),
+            pins: vec![Pin::at((0, 0, 0)), Pin::at((0, 0, 3))],
+            priority: 0,
+        }];
+        maze.process_nets(&nets).unwrap();
+
+        let raster = rasterize(&maze, 2);
+        assert_eq!((raster.width, raster.height), (18, 4));
+        assert_eq!(raster.pixels.len(), 18 * 4 * 3);
+        let pixel = |x: usize, y: usize| {
+            let at = (y * raster.width + x) * 3;
+            [
+                raster.pixels[at],
+                raster.pixels[at + 1],
+                raster.pixels[at + 2],
+            ]
+        };
+        assert_eq!(pixel(0, 0), net_rgb(1));
+        assert_eq!(pixel(1, 1), net_rgb(1));
+        assert_eq!(pixel(4, 0), OBSTACLE_COLOR);
+        assert_eq!(pixel(8, 3), GAP_COLOR);
+        // layer 2 starts after the gap, its cell (1,0) is blocked
+        assert_eq!(pixel(10, 2), OBSTACLE_COLOR);
+        let vias = (0..raster.width * raster.height)
+            .filter(|&i| pixel(i % raster.width, i / raster.width) == VIA_COLOR)
+            .count();
+        assert!(vias > 0 && vias % 4 == 0);
+
+        let ppm = raster.to_ppm();
+        assert!(ppm.starts_with(b"P6\n18 4\n255\n"));
+        assert_eq!(ppm.len(), 12 + raster.pixels.len());
+
+        let png = raster.to_png();
+        assert_eq!(
+            &png[..8],
+            &[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n']
+        );
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 18);
+        assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), 4);
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let idat = &png[41..41 + idat_len];
+        let crc = u32::from_be_bytes(png[41 + idat_len..45 + idat_len].try_into().unwrap());
+        assert_eq!(crc, crc32(&png[37..41 + idat_len]));
+        let scanlines = inflate_stored(idat);
+        assert_eq!(scanlines.len(), 4 * (1 + 18 * 3));
+        for (y, line) in scanlines.chunks(1 + 18 * 3).enumerate() {
+            assert_eq!(line[0], 0);
+            assert_eq!(&line[1..], &raster.pixels[y * 54..(y + 1) * 54]);
+        }
+        assert!(png.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]));
+    }
+
+    #[test]
+    fn test_net_colors_and_empty_image() {
+        assert_eq!(net_rgb(1), [195, 34, 34]); // hsl(0, 70%, 45%)
+        let colors: Vec<[u8; 3]> = (1..=8).map(net_rgb).collect();
+        for (i, color) in colors.iter().enumerate() {
+            assert!(!colors[i + 1..].contains(color));
+        }
+        let empty = Raster {
+            width: 0,
+            height: 0,
+            pixels: vec![],
+        };
+        assert!(empty.to_png().ends_with(&[0xae, 0x42, 0x60, 0x82]));
+    }
+}
@@ -0,0 +1,236 @@
+use crate::lee_maze::lee_maze::SourceStrategy;
+use crate::Coord;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetStatus {
+    Routed,  // every pin is connected
+    Partial, // some of the pins are connected to each other
+    Failed,  // no connection was made at all
+}
+
+// Straight run of wire within one layer, both ends included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub layer: usize,
+    pub start: (usize, usize), // x,y
+    pub end: (usize, usize),
+}
+
+// Connection between two neighbouring layers at one grid position
+#[derive(Debug, Clone, PartialEq)]
+pub struct Via {
+    pub x: usize,
+    pub y: usize,
+    pub lower: usize,
+    pub upper: usize,
+}
+
+// Two cells of different nets on one layer closer than its minimum spacing
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpacingViolation {
+    pub layer: usize,
+    pub nets: (String, String),
+    pub cells: (Coord, Coord),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetResult {
+    pub name: String,
+    pub status: NetStatus,
+    pub cells: Vec<Coord>, // the source pin first, then every path from the tree to the pin it reached
+    pub pins: Vec<Vec<Coord>>, // the cells of every pin, in input order
+    pub paths: Vec<Vec<Coord>>, // the wires as they were laid, each from a cell already on the net
+    pub segments: Vec<Segment>,
+    pub vias: Vec<Via>,
+    pub cost: u32,
+    pub wirelength: u32, // steps within a layer, vias not included
+    pub steiner_estimate: Option<u32>,
+    pub source_strategy: SourceStrategy,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoutingResult {
+    pub nets: Vec<NetResult>, // in input order
+    pub expanded_cells: usize,
+    pub violations: Vec<SpacingViolation>,
+}
+
+impl NetResult {
+    // Splits the steps of a route into straight segments and vias. Steps are (from, to) pairs in
+    // route order, so a segment continues as long as the next step keeps going the same way
+    pub fn from_steps(steps: &[(Coord, Coord)]) -> (Vec<Segment>, Vec<Via>) {
+        let mut segments: Vec<Segment> = vec![];
+        let mut vias = vec![];
+        let mut direction: Option<(isize, isize)> = None;
+
+        for &(from, to) in steps {
+            if from.0 != to.0 {
+                vias.push(Via {
+                    x: to.1,
+                    y: to.2,
+                    lower: from.0.min(to.0),
+                    upper: from.0.max(to.0),
+                });
+                direction = None;
+                continue;
+            }
+
+            let step = (
+                to.1 as isize - from.1 as isize,
+                to.2 as isize - from.2 as isize,
+            );
+            match segments.last_mut() {
+                Some(segment)
+                    if direction == Some(step)
+                        && segment.layer == from.0
+                        && segment.end == (from.1, from.2) =>
+                {
+                    segment.end = (to.1, to.2);
+                }
+                _ => segments.push(Segment {
+                    layer: from.0,
+                    start: (from.1, from.2),
+                    end: (to.1, to.2),
+                }),
+            }
+            direction = Some(step);
+        }
+        (segments, vias)
+    }
+
+    // Joins the steps of a route into the walks they make, a new walk starts wherever a step does
+    // not leave from the cell the one before it reached
+    pub fn paths_from_steps(steps: &[(Coord, Coord)]) -> Vec<Vec<Coord>> {
+        let mut paths: Vec<Vec<Coord>> = vec![];
+        for &(from, to) in steps {
+            match paths.last_mut() {
+                Some(path) if path.last() == Some(&from) => path.push(to),
+                _ => paths.push(vec![from, to]),
+            }
+        }
+        paths
+    }
+}
+
+impl RoutingResult {
+    pub fn total_cost(&self) -> u32 {
+        self.nets.iter().map(|net| net.cost).sum()
+    }
+
+    pub fn failed_nets(&self) -> Vec<&NetResult> {
+        self.nets
+            .iter()
+            .filter(|net| net.status != NetStatus::Routed)
+            .collect()
+    }
+
+    pub fn print_report(&self) {
+        for net in &self.nets {
+            let (l, x, y) = net.cells[0];
+            print!(
+                "Net {}: {:?}, source ({},{},{}) by {:?}, cost {}, wirelength {}, {} segments, {} vias",
+                net.name,
+                net.status,
+                l + 1,
+                x,
+                y,
+                net.source_strategy,
+                net.cost,
+                net.wirelength,
+                net.segments.len(),
+                net.vias.len()
+            );
+            if let Some(estimate) = net.steiner_estimate {
+                print!(", Steiner estimate {}", estimate);
+            }
+            println!();
+        }
+        for violation in &self.violations {
+            let ((_, x1, y1), (_, x2, y2)) = violation.cells;
+            println!(
+                "Spacing violation on layer {}: {} at ({},{}) and {} at ({},{})",
+                violation.layer + 1,
+                violation.nets.0,
+                x1,
+                y1,
+                violation.nets.1,
+                x2,
+                y2
+            );
+        }
+        println!(
+            "Total cost: {}, expanded cells: {}",
+            self.total_cost(),
+            self.expanded_cells
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::routing_result::routing_result::{NetResult, Segment, Via};
+
+    #[test]
+    fn test_steps_merge_into_segments() {
+        let steps = vec![
+            ((0, 0, 0), (0, 0, 1)),
+            ((0, 0, 1), (0, 0, 2)),
+            ((0, 0, 2), (0, 1, 2)),
+            ((0, 1, 2), (1, 1, 2)),
+            ((1, 1, 2), (1, 2, 2)),
+            ((1, 2, 2), (1, 3, 2)),
+            // next path starts elsewhere on the tree
+            ((0, 0, 1), (0, 1, 1)),
+        ];
+        let (segments, vias) = NetResult::from_steps(&steps);
+        assert_eq!(
+            segments,
+            vec![
+                Segment {
+                    layer: 0,
+                    start: (0, 0),
+                    end: (0, 2)
+                },
+                Segment {
+                    layer: 0,
+                    start: (0, 2),
+                    end: (1, 2)
+                },
+                Segment {
+                    layer: 1,
+                    start: (1, 2),
+                    end: (3, 2)
+                },
+                Segment {
+                    layer: 0,
+                    start: (0, 1),
+                    end: (1, 1)
+                },
+            ]
+        );
+        assert_eq!(
+            vias,
+            vec![Via {
+                x: 1,
+                y: 2,
+                lower: 0,
+                upper: 1
+            }]
+        );
+        assert_eq!(
+            NetResult::paths_from_steps(&steps),
+            vec![
+                vec![
+                    (0, 0, 0),
+                    (0, 0, 1),
+                    (0, 0, 2),
+                    (0, 1, 2),
+                    (1, 1, 2),
+                    (1, 2, 2),
+                    (1, 3, 2)
+                ],
+                vec![(0, 0, 1), (0, 1, 1)],
+            ]
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use std::fs;
+
+use crate::{Coord, RouterError, RoutingResult};
+
+// The routing result as text, one line per wire in the style of the input's net lines, e.g.
+//   net1 (1,0,0) (1,0,1) (2,0,1) (2,1,1)
+// with layers numbered from 1. The cells of a line are in the order the wire was laid, from a pin
+// or a cell the net already has to the pin it reached. A net of several wires has a line for each
+// of them in route order, and a net without any a line with just its name
+pub fn to_solution(result: &RoutingResult) -> String {
+    let mut text = String::new();
+    for net in &result.nets {
+        if net.paths.is_empty() {
+            text.push_str(&net.name);
+            text.push('\n');
+        }
+        for path in &net.paths {
+            text.push_str(&net.name);
+            for &(l, x, y) in path {
+                text.push_str(&format!(" ({},{},{})", l + 1, x, y));
+            }
+            text.push('\n');
+        }
+    }
+    text
+}
+
+// The wires of a solution file, ready for Maze::load_routes
+pub fn read_solution(filename: &str) -> Result<Vec<(String, Vec<Coord>)>, RouterError> {
+    let contents = fs::read_to_string(filename).map_err(|err| RouterError::Io {
+        path: filename.to_string(),
+        message: err.to_string(),
+    })?;
+    parse_solution(&contents)
+}
+
+// One (net, cells) pair per line, blank lines skipped. A line with only a name has no cells
+pub fn parse_solution(text: &str) -> Result<Vec<(String, Vec<Coord>)>, RouterError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            parse_wire(line).map_err(|message| RouterError::Parse {
+                line: i + 1,
+                message,
+            })
+        })
+        .collect()
+}
+
+fn parse_wire(line: &str) -> Result<(String, Vec<Coord>), &'static str> {
+    let line = line.trim();
+    let (name, tuples) = line.split_at(line.find('(').unwrap_or(line.len()));
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Missing net name");
+    }
+    if name.contains(char::is_whitespace) {
+        return Err("Net names cannot contain whitespace");
+    }
+    let cells = tuples
+        .split('(')
+        .skip(1)
+        .map(parse_cell)
+        .collect::<Result<_, _>>()?;
+    Ok((name.to_string(), cells))
+}
+
+// The inside of a (layer,x,y) tuple and its closing bracket, the layer back to 0-based
+fn parse_cell(tuple: &str) -> Result<Coord, &'static str> {
+    let err = "Invalid cell format. Expected format like (1,0,0)";
+    let values: Vec<usize> = tuple
+        .trim()
+        .strip_suffix(')')
+        .ok_or(err)?
+        .split(',')
+        .map(|s| s.trim().parse::<usize>().map_err(|_| err))
+        .collect::<Result<_, _>>()?;
+    match values[..] {
+        [l, x, y] => Ok((l.checked_sub(1).ok_or("Layers are numbered from 1")?, x, y)),
+        _ => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::solution::solution::{parse_solution, to_solution};
+    use crate::{Config, Maze, Net, NetStatus, Pin, RouterError};
+
+    #[test]
+    fn test_write_solution() {
+        let config = Config::build("test_cases/test_case1.txt").unwrap();
+        let result = Maze::from_config(&config)
+            .unwrap()
+            .process_nets(&config.nets)
+            .unwrap();
+        assert_eq!(
+            to_solution(&result),
+            "net1 (1,0,0) (2,0,0) (2,1,0) (2,2,0) (2,3,0) (1,3,0) (1,3,1) (1,3,2) (2,3,2) \
+             (2,2,2) (2,1,2) (2,0,2) (1,0,2) (1,0,3) (1,0,4) (2,0,4) (2,1,4) (2,2,4) (2,3,4) \
+             (2,4,4) (1,4,4)\n"
+        );
+    }
+
+    #[test]
+    fn test_solution_round_trip() {
+        for case in [
+            "test_case4",
+            "test_case9",
+            "test_case10",
+            "test_case11",
+            "test_case12",
+        ] {
+            let config = Config::build(&format!("test_cases/{}.txt", case)).unwrap();
+            let mut routed = Maze::from_config(&config).unwrap();
+            let result = routed.process_nets(&config.nets).unwrap();
+
+            let paths = parse_solution(&to_solution(&result)).unwrap();
+            let mut loaded = Maze::from_config(&config).unwrap();
+            let reloaded = loaded.load_routes(&config.nets, &paths).unwrap();
+            for (net, again) in result.nets.iter().zip(&reloaded.nets) {
+                assert_eq!(net.status, again.status, "{} of {}", net.name, case);
+                assert_eq!(net.cells, again.cells, "{} of {}", net.name, case);
+                assert_eq!(net.paths, again.paths);
+                assert_eq!(net.segments, again.segments);
+                assert_eq!(net.vias, again.vias);
+                assert_eq!(net.wirelength, again.wirelength);
+                assert_eq!(net.cost, again.cost);
+            }
+            for l in 0..config.layers {
+                for x in 0..routed.width() {
+                    for y in 0..routed.height() {
+                        assert_eq!(routed.cell((l, x, y)), loaded.cell((l, x, y)));
+                        assert_eq!(routed.is_via((l, x, y)), loaded.is_via((l, x, y)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_partial_solution_round_trip() {
+        // the last pin of net1 is walled in on both layers
+        let nets = vec![
+            Net {
+                _net_name: "net1".to_string(),
+                pins: vec![Pin::at((0, 0, 0)), Pin::at((0, 0, 4)), Pin::at((0, 4, 4))],
+                priority: 0,
+            },
+            Net {
+                _net_name: "net2".to_string(),
+                pins: vec![Pin::at((0, 2, 0)), Pin::at((0, 2, 4))],
+                priority: 0,
+            },
+        ];
+        let maze = || {
+            let mut maze = Maze::new(5, 5, 2, 10, 5);
+            maze.initialize_obstacles(&vec![(0, 3, 4), (0, 4, 3), (1, 4, 4)]);
+            maze
+        };
+        let result = maze().process_nets(&nets).unwrap();
+        assert_eq!(result.nets[0].status, NetStatus::Partial);
+
+        let paths = parse_solution(&to_solution(&result)).unwrap();
+        let reloaded = maze().load_routes(&nets, &paths).unwrap();
+        for (net, again) in result.nets.iter().zip(&reloaded.nets) {
+            assert_eq!(net.status, again.status, "{}", net.name);
+            assert_eq!(net.cells, again.cells);
+            assert_eq!(net.cost, again.cost);
+        }
+
+        // a wire that stops short joins no second pin, one from the second pin to the first does
+        let short = parse_solution("net1 (1,0,4) (1,0,3)\nnet1 (1,0,3) (1,0,2)").unwrap();
+        let loaded = maze().load_routes(&nets[..1], &short).unwrap();
+        assert_eq!(loaded.nets[0].status, NetStatus::Failed);
+        let joined = parse_solution("net1 (1,0,4) (1,0,3) (1,0,2) (1,0,1) (1,0,0)").unwrap();
+        let loaded = maze().load_routes(&nets[..1], &joined).unwrap();
+        assert_eq!(loaded.nets[0].status, NetStatus::Partial);
+    }
+
+    #[test]
+    fn test_parse_solution() {
+        let text = "net1 (1,0,0) (2,0,0)\n\n  net2\nbus[0] (1,4,4)(1,4,3)\n";
+        assert_eq!(
+            parse_solution(text).unwrap(),
+            vec![
+                ("net1".to_string(), vec![(0, 0, 0), (1, 0, 0)]),
+                ("net2".to_string(), vec![]),
+                ("bus[0]".to_string(), vec![(0, 4, 4), (0, 4, 3)]),
+            ]
+        );
+        let errors = [
+            ("net1 (1,0,0)\n(1,0,1)", 2, "Missing net name"),
+            ("net 1 (1,0,0)", 1, "Net names cannot contain whitespace"),
+            ("net1 (0,0,0)", 1, "Layers are numbered from 1"),
+            (
+                "net1 (1,0,0) (1,0)",
+                1,
+                "Invalid cell format. Expected format like (1,0,0)",
+            ),
+            (
+                "\nnet1 (1,0,0 (1,0,1)",
+                2,
+                "Invalid cell format. Expected format like (1,0,0)",
+            ),
+        ];
+        for (text, line, message) in errors {
+            assert_eq!(
+                parse_solution(text).unwrap_err(),
+                RouterError::Parse { line, message }
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_invalid_routes() {
+        let config = Config::build("test_cases/test_case1.txt").unwrap();
+        let wire = |cells: &[(usize, usize, usize)]| vec![("net1".to_string(), cells.to_vec())];
+        let invalid = |coord, message| RouterError::InvalidRoute {
+            net: "net1".to_string(),
+            coord,
+            message,
+        };
+        let cases = [
+            (
+                wire(&[(0, 0, 0), (0, 1, 0), (0, 3, 0)]),
+                invalid((0, 3, 0), "not next to the cell before it"),
+            ),
+            (
+                wire(&[(0, 0, 0), (0, 0, 1)]),
+                invalid((0, 0, 1), "blocked or used by another net"),
+            ),
+            (
+                wire(&[(0, 1, 0), (0, 2, 0)]),
+                invalid((0, 1, 0), "the wire does not start on the net"),
+            ),
+            (
+                wire(&[(0, 4, 4), (0, 5, 4)]),
+                invalid((0, 5, 4), "outside the grid"),
+            ),
+        ];
+        for (paths, err) in cases {
+            assert_eq!(
+                Maze::from_config(&config)
+                    .unwrap()
+                    .load_routes(&config.nets, &paths)
+                    .unwrap_err(),
+                err
+            );
+        }
+
+        let unknown = vec![("net9".to_string(), vec![(0, 0, 0)])];
+        assert_eq!(
+            Maze::from_config(&config)
+                .unwrap()
+                .load_routes(&config.nets, &unknown)
+                .unwrap_err(),
+            RouterError::UnknownName {
+                kind: "net",
+                name: "net9".to_string()
+            }
+        );
+
+        // the layer spacing, via stacking and via spacing rules of the design hold as well
+        let spaced = Config::build("test_cases/test_case9.txt").unwrap();
+        let close = vec![("net1".to_string(), vec![(0, 2, 0), (0, 3, 0)])];
+        assert_eq!(
+            Maze::from_config(&spaced)
+                .unwrap()
+                .load_routes(&spaced.nets, &close)
+                .unwrap_err(),
+            RouterError::InvalidRoute {
+                net: "net1".to_string(),
+                coord: (0, 3, 0),
+                message: "closer to another net than the layer spacing"
+            }
+        );
+        let vias = Config::build("test_cases/test_case10.txt").unwrap();
+        let stacked = vec![("net3".to_string(), vec![(0, 0, 5), (1, 0, 5), (2, 0, 5)])];
+        let crowded = vec![
+            ("net1".to_string(), vec![(0, 0, 0), (1, 0, 0)]),
+            ("net2".to_string(), vec![(0, 1, 1), (1, 1, 1)]),
+        ];
+        for (paths, net, coord, message) in [
+            (stacked, "net3", (2, 0, 5), "a via stacked on another"),
+            (
+                crowded,
+                "net2",
+                (1, 1, 1),
+                "a via closer to another than the via spacing",
+            ),
+        ] {
+            assert_eq!(
+                Maze::from_config(&vias)
+                    .unwrap()
+                    .load_routes(&vias.nets, &paths)
+                    .unwrap_err(),
+                RouterError::InvalidRoute {
+                    net: net.to_string(),
+                    coord,
+                    message
+                }
+            );
+        }
+
+        // a wire that stops short of the other pin leaves the net unrouted, with what it costs
+        let result = Maze::from_config(&config)
+            .unwrap()
+            .load_routes(&config.nets, &wire(&[(0, 0, 0), (1, 0, 0), (1, 1, 0)]))
+            .unwrap();
+        assert_eq!(result.nets[0].status, NetStatus::Failed);
+        assert_eq!(result.nets[0].cost, 11);
+        assert_eq!(result.nets[0].wirelength, 1);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::BTreeSet;
+
+static MAX_ONE_STEINER_TERMINALS: usize = 32; // above this the plain spanning tree is used
+
+pub type Point = (usize, usize); // x,y
+
+#[derive(Debug, Clone)]
+pub struct SteinerTree {
+    pub points: Vec<Point>, // the terminals first, then the added Steiner points
+    pub terminals: usize,   // how many of the points are terminals
+    pub edges: Vec<(usize, usize)>, // indices into points
+    pub length: u32,        // rectilinear wirelength of the tree
+}
+
+impl SteinerTree {
+    // The other points in breadth-first order from the given root
+    pub fn walk_from(&self, root: usize) -> Vec<usize> {
+        let mut adjacent: Vec<Vec<usize>> = vec![vec![]; self.points.len()];
+        for &(a, b) in &self.edges {
+            adjacent[a].push(b);
+            adjacent[b].push(a);
+        }
+
+        let mut visited = vec![false; self.points.len()];
+        visited[root] = true;
+        let mut order = vec![];
+        let mut frontier = vec![root];
+        let mut next = 0;
+        while next < frontier.len() {
+            let point = frontier[next];
+            next += 1;
+            for &neighbor in &adjacent[point] {
+                if !visited[neighbor] {
+                    visited[neighbor] = true;
+                    order.push(neighbor);
+                    frontier.push(neighbor);
+                }
+            }
+        }
+        order
+    }
+}
+
+fn distance(a: Point, b: Point) -> u32 {
+    (a.0.abs_diff(b.0) + a.1.abs_diff(b.1)) as u32
+}
+
+// Prim's algorithm on the complete graph with Manhattan distances
+fn spanning_tree(points: &[Point]) -> (u32, Vec<(usize, usize)>) {
+    if points.is_empty() {
+        return (0, vec![]);
+    }
+    let mut in_tree = vec![false; points.len()];
+    let mut best: Vec<(u32, usize)> = vec![(u32::MAX, 0); points.len()]; // (distance, closest tree point)
+    let mut length = 0;
+    let mut edges = vec![];
+
+    let mut current = 0;
+    in_tree[0] = true;
+    for _ in 1..points.len() {
+        for (i, &point) in points.iter().enumerate() {
+            if !in_tree[i] && distance(points[current], point) < best[i].0 {
+                best[i] = (distance(points[current], point), current);
+            }
+        }
+        let next = (0..points.len())
+            .filter(|&i| !in_tree[i])
+            .min_by_key(|&i| best[i].0)
+            .unwrap();
+        in_tree[next] = true;
+        length += best[next].0;
+        edges.push((best[next].1, next));
+        current = next;
+    }
+    (length, edges)
+}
+
+// Rectilinear Steiner tree estimate by iterated 1-Steiner: keep adding the Hanan grid point that
+// shortens the spanning tree the most, dropping added points that end up with two or fewer edges
+pub fn rectilinear_steiner_tree(terminals: &[Point]) -> SteinerTree {
+    let mut points: Vec<Point> = terminals.to_vec();
+
+    if terminals.len() <= MAX_ONE_STEINER_TERMINALS {
+        let xs: BTreeSet<usize> = terminals.iter().map(|p| p.0).collect();
+        let ys: BTreeSet<usize> = terminals.iter().map(|p| p.1).collect();
+        let (mut length, _) = spanning_tree(&points);
+
+        loop {
+            let mut improvement: Option<(u32, Point)> = None;
+            for &x in &xs {
+                for &y in &ys {
+                    if points.contains(&(x, y)) {
+                        continue;
+                    }
+                    points.push((x, y));
+                    let (candidate, _) = spanning_tree(&points);
+                    points.pop();
+                    if candidate < improvement.map_or(length, |(best, _)| best) {
+                        improvement = Some((candidate, (x, y)));
+                    }
+                }
+            }
+            let Some((_, point)) = improvement else {
+                break;
+            };
+            points.push(point);
+
+            // Steiner points of degree two or less never help a rectilinear tree
+            let (_, edges) = spanning_tree(&points);
+            let mut degree = vec![0; points.len()];
+            for &(a, b) in &edges {
+                degree[a] += 1;
+                degree[b] += 1;
+            }
+            let mut index = 0;
+            points.retain(|_| {
+                index += 1;
+                index <= terminals.len() || degree[index - 1] > 2
+            });
+            length = spanning_tree(&points).0;
+        }
+    }
+
+    let (length, edges) = spanning_tree(&points);
+    SteinerTree {
+        points,
+        terminals: terminals.len(),
+        edges,
+        length,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::steiner::steiner::rectilinear_steiner_tree;
+
+    #[test]
+    fn test_steiner_point_shortens_tree() {
+        // spanning tree needs 6, the center point brings it down to 4
+        let tree = rectilinear_steiner_tree(&[(1, 0), (0, 1), (2, 1), (1, 2)]);
+        assert_eq!(tree.length, 4);
+        assert_eq!(tree.points.len(), 5);
+        assert_eq!(tree.points[4], (1, 1));
+    }
+
+    #[test]
+    fn test_two_terminals_need_no_steiner_point() {
+        let tree = rectilinear_steiner_tree(&[(0, 0), (3, 4)]);
+        assert_eq!(tree.length, 7);
+        assert_eq!(tree.points.len(), 2);
+        assert_eq!(tree.edges.len(), 1);
+    }
+
+    #[test]
+    fn test_walk_visits_every_point_once() {
+        let tree = rectilinear_steiner_tree(&[(0, 0), (0, 4), (2, 2)]);
+        assert_eq!(tree.length, 6);
+        let order = tree.walk_from(0);
+        assert_eq!(order.len(), tree.points.len() - 1);
+        assert!(order.iter().all(|&point| point != 0));
+    }
+}
@@ -0,0 +1,311 @@
+use std::fmt::Write;
+
+use crate::{Blockage, Maze, NetStatus, RoutingResult};
+
+static CELL_SIZE: usize = 10; // pixels per grid cell
+static MARGIN: usize = 24; // around and between the panels, room for their titles
+static LEGEND_LINE: usize = 16;
+static OBSTACLE_COLOR: &str = "#888888";
+static LEGEND_PIN_COLOR: &str = "#dddddd"; // pins take their net's colour, the legend shows a neutral one
+static OVERLAY_OPACITY: f32 = 0.6; // of each layer's wires when all layers share one panel
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SvgView {
+    Layers,  // one panel per layer, side by side
+    Overlay, // every layer in one panel, wires drawn see-through
+}
+
+// Fill colour of the net at the given position in the result, hues a golden angle apart so
+// neighbouring nets never look alike
+pub fn net_color(index: usize) -> String {
+    format!("hsl({:.1}, 70%, 45%)", (index as f64 * 137.508) % 360.0)
+}
+
+// The routed maze as an SVG document. Rows (x) run down and columns (y) across, as in
+// print_layers_side_by_side. Obstacles are gray, pins are outlined squares, vias are circles and
+// a legend under the panels names the colour of every net
+pub fn to_svg(maze: &Maze, result: &RoutingResult, view: SvgView) -> String {
+    let layers = maze.layer_stack().len();
+    let panel = (maze.height() * CELL_SIZE, maze.width() * CELL_SIZE);
+    let panels = match view {
+        SvgView::Layers => layers,
+        SvgView::Overlay => 1,
+    };
+    let legend_top = MARGIN + panel.1 + MARGIN;
+    let width = (MARGIN + panels * (panel.0 + MARGIN)).max(240);
+    let height = legend_top + (result.nets.len() + 3) * LEGEND_LINE + MARGIN;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="sans-serif" font-size="12">"#,
+        w = width,
+        h = height
+    )
+    .unwrap();
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#).unwrap();
+
+    for p in 0..panels {
+        let (title, shown): (String, Vec<usize>) = match view {
+            SvgView::Layers => (format!("Layer {}", p + 1), vec![p]),
+            SvgView::Overlay => ("All layers".to_string(), (0..layers).collect()),
+        };
+        writeln!(
+            svg,
+            r#"<g transform="translate({},{})">"#,
+            MARGIN + p * (panel.0 + MARGIN),
+            MARGIN
+        )
+        .unwrap();
+        writeln!(svg, r#"<text x="0" y="-8">{}</text>"#, title).unwrap();
+        writeln!(
+            svg,
+            "<rect width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#cccccc\"/>",
+            panel.0, panel.1
+        )
+        .unwrap();
+        draw_panel(&mut svg, maze, result, view, &shown);
+        writeln!(svg, "</g>").unwrap();
+    }
+
+    draw_legend(&mut svg, result, legend_top);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// Obstacles, then wires, then pins and vias on top, for the given layers
+fn draw_panel(
+    svg: &mut String,
+    maze: &Maze,
+    result: &RoutingResult,
+    view: SvgView,
+    shown: &[usize],
+) {
+    let center = |v: usize| v * CELL_SIZE + CELL_SIZE / 2;
+    let obstacle_opacity = match view {
+        SvgView::Layers => 1.0,
+        SvgView::Overlay => 1.0 / shown.len() as f32, // darker where more layers are blocked
+    };
+    for &l in shown {
+        for blockage in maze.blockages() {
+            if blockage.layer.is_none_or(|layer| layer == l) {
+                draw_blockage(svg, maze, blockage, obstacle_opacity);
+            }
+        }
+    }
+
+    let wire_opacity = match view {
+        SvgView::Layers => 1.0,
+        SvgView::Overlay => OVERLAY_OPACITY,
+    };
+    for (index, net) in result.nets.iter().enumerate() {
+        let color = net_color(index);
+        for segment in net.segments.iter().filter(|s| shown.contains(&s.layer)) {
+            writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}" stroke-linecap="square" stroke-opacity="{}"/>"#,
+                center(segment.start.1),
+                center(segment.start.0),
+                center(segment.end.1),
+                center(segment.end.0),
+                color,
+                CELL_SIZE / 2,
+                wire_opacity
+            )
+            .unwrap();
+        }
+    }
+
+    for (index, net) in result.nets.iter().enumerate() {
+        let color = net_color(index);
+        for &(l, x, y) in net.pins.iter().flatten() {
+            if shown.contains(&l) {
+                writeln!(
+                    svg,
+                    r#"<rect x="{}" y="{}" width="{s}" height="{s}" fill="{}" stroke="black"/>"#,
+                    y * CELL_SIZE,
+                    x * CELL_SIZE,
+                    color,
+                    s = CELL_SIZE
+                )
+                .unwrap();
+            }
+        }
+        for via in &net.vias {
+            if shown.contains(&via.lower) || shown.contains(&via.upper) {
+                writeln!(
+                    svg,
+                    r#"<circle cx="{}" cy="{}" r="{}" fill="white" stroke="{}" stroke-width="2"/>"#,
+                    center(via.y),
+                    center(via.x),
+                    CELL_SIZE * 3 / 10,
+                    color
+                )
+                .unwrap();
+            }
+        }
+    }
+}
+
+// The part of the blockage on the grid, nothing if it lies outside
+fn draw_blockage(svg: &mut String, maze: &Maze, blockage: &Blockage, opacity: f32) {
+    if blockage.rows.0 >= maze.width() || blockage.cols.0 >= maze.height() {
+        return;
+    }
+    let rows = blockage.rows.1.min(maze.width() - 1) - blockage.rows.0 + 1;
+    let cols = blockage.cols.1.min(maze.height() - 1) - blockage.cols.0 + 1;
+    writeln!(
+        svg,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" fill-opacity="{}"/>"#,
+        blockage.cols.0 * CELL_SIZE,
+        blockage.rows.0 * CELL_SIZE,
+        cols * CELL_SIZE,
+        rows * CELL_SIZE,
+        OBSTACLE_COLOR,
+        opacity
+    )
+    .unwrap();
+}
+
+// A swatch and name per net, then the symbols for obstacles, pins and vias
+fn draw_legend(svg: &mut String, result: &RoutingResult, top: usize) {
+    let swatch = |svg: &mut String, line: usize, shape: &str, label: &str| {
+        let y = top + line * LEGEND_LINE;
+        writeln!(
+            svg,
+            r#"<g transform="translate({},{})">{}<text x="{}" y="{}">{}</text></g>"#,
+            MARGIN,
+            y,
+            shape,
+            CELL_SIZE + 6,
+            CELL_SIZE,
+            label
+        )
+        .unwrap();
+    };
+    for (index, net) in result.nets.iter().enumerate() {
+        let mut label = escape(&net.name);
+        if net.status != NetStatus::Routed {
+            write!(label, " ({:?})", net.status).unwrap();
+        }
+        let shape = format!(
+            r#"<rect width="{s}" height="{s}" fill="{}"/>"#,
+            net_color(index),
+            s = CELL_SIZE
+        );
+        swatch(svg, index, &shape, &label);
+    }
+    let line = result.nets.len();
+    let obstacle = format!(
+        r#"<rect width="{s}" height="{s}" fill="{}"/>"#,
+        OBSTACLE_COLOR,
+        s = CELL_SIZE
+    );
+    swatch(svg, line, &obstacle, "obstacle");
+    let pin = format!(
+        r#"<rect width="{s}" height="{s}" fill="{}" stroke="black"/>"#,
+        LEGEND_PIN_COLOR,
+        s = CELL_SIZE
+    );
+    swatch(svg, line + 1, &pin, "pin");
+    let via = format!(
+        r#"<circle cx="{c}" cy="{c}" r="{}" fill="white" stroke="black" stroke-width="2"/>"#,
+        CELL_SIZE * 3 / 10,
+        c = CELL_SIZE / 2
+    );
+    swatch(svg, line + 2, &via, "via");
+}
+
+// Net names are free text, the characters XML reserves have to be written as entities
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::svg::svg::{escape, net_color, to_svg, SvgView};
+    use crate::{Blockage, Maze, Net, Pin, RoutingResult};
+
+    // Two nets on a 2-layer 6x6 grid with an all-layer blockage in the middle, the second net
+    // has to change layers to get past the first
+    fn routed_maze() -> (Maze, RoutingResult) {
+        let mut maze = Maze::new(6, 6, 2, 1, 10);
+        maze.initialize_blockages(&[Blockage {
+            name: Some("macro".to_string()),
+            layer: None,
+            rows: (2, 3),
+            cols: (2, 3),
+        }]);
+        let nets = vec![
+            Net {
+                _net_name: "a&b".to_string(),
+                pins: vec![Pin::at((0, 0, 0)), Pin::at((0, 0, 5))],
+                priority: 0,
+            },
+            Net {
+                _net_name: "clk".to_string(),
+                pins: vec![Pin::at((0, 5, 1)), Pin::at((1, 0, 1))],
+                priority: 0,
+            },
+        ];
+        let result = maze.process_nets(&nets).unwrap();
+        (maze, result)
+    }
+
+    fn count(svg: &str, element: &str) -> usize {
+        svg.matches(element).count()
+    }
+
+    #[test]
+    fn test_layers_view() {
+        let (maze, result) = routed_maze();
+        let svg = to_svg(&maze, &result, SvgView::Layers);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert!(svg.contains(">Layer 1<") && svg.contains(">Layer 2<"));
+
+        let segments: usize = result.nets.iter().map(|net| net.segments.len()).sum();
+        let vias: usize = result.nets.iter().map(|net| net.vias.len()).sum();
+        assert!(vias > 0);
+        assert_eq!(count(&svg, "<line"), segments);
+        // a via shows on both of its layers, plus the one in the legend
+        assert_eq!(count(&svg, "<circle"), 2 * vias + 1);
+        // the blockage once per layer, plus the one in the legend
+        assert_eq!(count(&svg, "fill=\"#888888\""), 3);
+        assert!(svg.contains(">a&amp;b<") && svg.contains(">clk<"));
+        assert!(svg.contains(&net_color(1)));
+        // the pin swatch is outlined like the pins, filled with no net's colour
+        assert_eq!(count(&svg, "fill=\"#dddddd\" stroke=\"black\""), 1);
+    }
+
+    #[test]
+    fn test_overlay_view() {
+        let (maze, result) = routed_maze();
+        let svg = to_svg(&maze, &result, SvgView::Overlay);
+        assert!(svg.contains(">All layers<") && !svg.contains(">Layer 1<"));
+        let vias: usize = result.nets.iter().map(|net| net.vias.len()).sum();
+        assert_eq!(count(&svg, "<circle"), vias + 1);
+        assert_eq!(count(&svg, "stroke-opacity=\"0.6\""), count(&svg, "<line"));
+        assert_eq!(count(&svg, "fill-opacity=\"0.5\""), 2);
+    }
+
+    #[test]
+    fn test_net_colors_and_escaping() {
+        let colors: Vec<String> = (0..8).map(net_color).collect();
+        for (i, color) in colors.iter().enumerate() {
+            assert!(!colors[i + 1..].contains(color));
+        }
+        assert_eq!(escape("d<3> & 'q'"), "d&lt;3&gt; &amp; &apos;q&apos;");
+    }
+}